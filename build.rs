@@ -0,0 +1,27 @@
+use std::{path::Path, process::Command};
+
+/// Embeds the git commit as `GIT_SHA` for the `/version` endpoint. A `GIT_SHA`
+/// set in the build environment wins, for builds outside a checkout.
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    for path in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    let sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        let output = Command::new("git")
+            .args(["rev-parse", "--short=12", "HEAD"])
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=GIT_SHA={}",
+        sha.unwrap_or_else(|| "unknown".to_string())
+    );
+}
@@ -0,0 +1,133 @@
+#!/usr/bin/env python3
+"""Derives data/gazetteer.csv from open US place and ZIP code datasets.
+
+Inputs:
+
+- `zips.json.bz2` from the `zipcodes` project (MIT licensed,
+  https://github.com/seanpianka/zipcodes, also published in the `zipcodes`
+  crate): every US ZIP code with its city, state and centroid.
+- `cities.csv` from the `reverse_geocoder` crate (https://github.com/gx0r/rrgeo):
+  the GeoNames `cities1000` extract of places with at least 1,000 people
+  (CC BY 4.0, https://www.geonames.org).
+
+Run:
+
+    scripts/build_gazetteer.py zips.json.bz2 cities.csv
+
+Every active, located ZIP code in a state or territory becomes a ZIP row. Places come
+from GeoNames; a city that appears only as a ZIP code's city is added as a
+place at the average of its ZIP centroids, so small towns resolve too.
+"""
+
+import argparse
+import bz2
+import csv
+import json
+from collections import defaultdict
+from pathlib import Path
+
+# Keep in step with gazetteer::STATES
+STATES = {
+    "Alabama": "AL", "Alaska": "AK", "Arizona": "AZ", "Arkansas": "AR",
+    "California": "CA", "Colorado": "CO", "Connecticut": "CT", "Delaware": "DE",
+    "District of Columbia": "DC", "Florida": "FL", "Georgia": "GA", "Hawaii": "HI",
+    "Idaho": "ID", "Illinois": "IL", "Indiana": "IN", "Iowa": "IA", "Kansas": "KS",
+    "Kentucky": "KY", "Louisiana": "LA", "Maine": "ME", "Maryland": "MD",
+    "Massachusetts": "MA", "Michigan": "MI", "Minnesota": "MN", "Mississippi": "MS",
+    "Missouri": "MO", "Montana": "MT", "Nebraska": "NE", "Nevada": "NV",
+    "New Hampshire": "NH", "New Jersey": "NJ", "New Mexico": "NM", "New York": "NY",
+    "North Carolina": "NC", "North Dakota": "ND", "Ohio": "OH", "Oklahoma": "OK",
+    "Oregon": "OR", "Pennsylvania": "PA", "Rhode Island": "RI",
+    "South Carolina": "SC", "South Dakota": "SD", "Tennessee": "TN", "Texas": "TX",
+    "Utah": "UT", "Vermont": "VT", "Virginia": "VA", "Washington": "WA",
+    "West Virginia": "WV", "Wisconsin": "WI", "Wyoming": "WY",
+    "American Samoa": "AS", "Guam": "GU", "Northern Mariana Islands": "MP",
+    "Puerto Rico": "PR", "Virgin Islands": "VI",
+}
+# GeoNames lists the territories as countries of their own
+TERRITORIES = {"AS", "GU", "MP", "PR", "VI"}
+
+HEADER = """\
+# Generated by scripts/build_gazetteer.py from the zipcodes ZIP code database
+# (MIT) and GeoNames cities1000 (CC BY 4.0, https://www.geonames.org).
+# Rows with an empty zip are places; rows with a zip are ZIP code centroids
+# labelled with their city.
+name,state,zip,latitude,longitude
+"""
+
+
+def normalize(name):
+    """Same as gazetteer::normalize, to tell whether a place is already known."""
+    words = name.lower().replace(".", " ").split()
+    return " ".join("saint" if word == "st" else word for word in words)
+
+
+def read_places(path):
+    places = []
+    with open(path, newline="", encoding="utf-8") as file:
+        for row in csv.DictReader(file):
+            country = row["cc"].strip()
+            if country == "US":
+                state = STATES.get(row["admin1"])
+            else:
+                state = country if country in TERRITORIES else None
+            if state is None:
+                continue
+            # Neighbourhoods are listed as "Westwood, Los Angeles"
+            name = row["name"].split(",")[0].strip()
+            places.append((name, state, float(row["lat"]), float(row["lon"])))
+    return places
+
+
+def read_zips(path):
+    codes = set(STATES.values())
+    with bz2.open(path) as file:
+        zips = json.load(file)
+    rows = []
+    for z in zips:
+        if not z["active"] or z["state"] not in codes or z["zip_code_type"] == "MILITARY":
+            continue
+        latitude, longitude = float(z["lat"] or 0), float(z["long"] or 0)
+        # Some ZIP codes without a location have a centroid of 0,0
+        if latitude == 0 and longitude == 0:
+            continue
+        rows.append((z["city"].replace(",", ""), z["state"], z["zip_code"], latitude, longitude))
+    return rows
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("zips", type=Path, help="zips.json.bz2 from the zipcodes project")
+    parser.add_argument("places", type=Path, help="cities.csv from the reverse_geocoder crate")
+    parser.add_argument(
+        "-o",
+        "--output",
+        type=Path,
+        default=Path(__file__).resolve().parent.parent / "data" / "gazetteer.csv",
+    )
+    args = parser.parse_args()
+
+    places = read_places(args.places)
+    zips = read_zips(args.zips)
+
+    known = {(normalize(name), state) for name, state, _, _ in places}
+    centroids = defaultdict(list)
+    for city, state, _, latitude, longitude in zips:
+        if (normalize(city), state) not in known:
+            centroids[(city, state)].append((latitude, longitude))
+    for (city, state), points in sorted(centroids.items()):
+        latitude = sum(point[0] for point in points) / len(points)
+        longitude = sum(point[1] for point in points) / len(points)
+        places.append((city, state, latitude, longitude))
+
+    with open(args.output, "w", encoding="utf-8") as file:
+        file.write(HEADER)
+        for name, state, latitude, longitude in places:
+            file.write(f"{name},{state},,{latitude:.4f},{longitude:.4f}\n")
+        for city, state, zip_code, latitude, longitude in sorted(zips, key=lambda z: z[2]):
+            file.write(f"{city},{state},{zip_code},{latitude:.4f},{longitude:.4f}\n")
+    print(f"Wrote {len(places)} places and {len(zips)} ZIP codes to {args.output}")
+
+
+if __name__ == "__main__":
+    main()
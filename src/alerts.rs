@@ -0,0 +1,441 @@
+//! Areas and filters for active alert queries.
+//!
+//! An area is a state or territory, a marine area, a marine region, or a list
+//! of zones, each sent to NWS as its own `/alerts/active` query parameter.
+//! [`areas_near`] finds the states and marine areas a radius search must
+//! query, from approximate bounding boxes.
+//!
+//! Severity, urgency, certainty, message type, status and event names are
+//! sent to NWS as `/alerts/active` query parameters. Event names are matched
+//! again here, ignoring case, and the result count is capped here because
+//! `/alerts/active` doesn't accept a limit.
+
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use rmcp::schemars;
+use serde::Deserialize;
+
+use crate::{
+    FeatureProps,
+    coordinates::{AREA_BOUNDS, Coordinates},
+    gazetteer,
+};
+
+/// Marine areas accepted by the `area` parameter, alongside state codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+pub enum MarineArea {
+    /// Western North Atlantic south of Currituck Beach Light, NC, including the Caribbean
+    AM,
+    /// Western North Atlantic from the Canadian border to Currituck Beach Light, NC
+    AN,
+    /// Gulf of Mexico
+    GM,
+    /// Lake St. Clair
+    LC,
+    /// Lake Erie
+    LE,
+    /// Lake Huron
+    LH,
+    /// Lake Michigan
+    LM,
+    /// Lake Ontario
+    LO,
+    /// Lake Superior
+    LS,
+    /// Central Pacific, including Hawaiian waters
+    PH,
+    /// North Pacific near Alaska, including the Bering Sea and Gulf of Alaska
+    PK,
+    /// Western Pacific, including Mariana Islands waters
+    PM,
+    /// South Central Pacific, including American Samoa waters
+    PS,
+    /// Eastern North Pacific along the US West Coast
+    PZ,
+    /// St. Lawrence River above St. Regis
+    SL,
+}
+
+impl MarineArea {
+    fn as_str(self) -> &'static str {
+        match self {
+            MarineArea::AM => "AM",
+            MarineArea::AN => "AN",
+            MarineArea::GM => "GM",
+            MarineArea::LC => "LC",
+            MarineArea::LE => "LE",
+            MarineArea::LH => "LH",
+            MarineArea::LM => "LM",
+            MarineArea::LO => "LO",
+            MarineArea::LS => "LS",
+            MarineArea::PH => "PH",
+            MarineArea::PK => "PK",
+            MarineArea::PM => "PM",
+            MarineArea::PS => "PS",
+            MarineArea::PZ => "PZ",
+            MarineArea::SL => "SL",
+        }
+    }
+}
+
+/// Marine regions accepted by the `region` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+pub enum Region {
+    /// Alaska
+    AL,
+    /// Atlantic
+    AT,
+    /// Great Lakes
+    GL,
+    /// Gulf of Mexico
+    GM,
+    /// Eastern Pacific
+    PA,
+    /// Central and Western Pacific
+    PI,
+}
+
+impl Region {
+    fn as_str(self) -> &'static str {
+        match self {
+            Region::AL => "AL",
+            Region::AT => "AT",
+            Region::GL => "GL",
+            Region::GM => "GM",
+            Region::PA => "PA",
+            Region::PI => "PI",
+        }
+    }
+}
+
+/// The area an alert query covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlertArea {
+    State(&'static str),
+    MarineArea(MarineArea),
+    Region(Region),
+    /// Forecast, county, fire weather or marine zone IDs, such as `WAZ558`.
+    Zones(Vec<String>),
+}
+
+impl AlertArea {
+    /// Picks the one area given, normalizing a state name such as
+    /// "California" to its code and checking zone IDs.
+    pub fn new(
+        state: Option<String>,
+        marine_area: Option<MarineArea>,
+        region: Option<Region>,
+        zone: Vec<String>,
+    ) -> Result<Self, String> {
+        let given = usize::from(state.is_some())
+            + usize::from(marine_area.is_some())
+            + usize::from(region.is_some())
+            + usize::from(!zone.is_empty());
+        if given != 1 {
+            return Err("Provide exactly one of state, marine_area, region or zone.".to_string());
+        }
+
+        if let Some(state) = state {
+            return gazetteer::state_code(&state)
+                .map(AlertArea::State)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown state \"{}\": use a two-letter code such as \"CA\" or a name such as \"California\". For coastal and offshore waters use marine_area.",
+                        state
+                    )
+                });
+        }
+        if let Some(marine_area) = marine_area {
+            return Ok(AlertArea::MarineArea(marine_area));
+        }
+        if let Some(region) = region {
+            return Ok(AlertArea::Region(region));
+        }
+
+        let zones = zone
+            .iter()
+            .map(|id| id.trim().to_ascii_uppercase())
+            .collect::<Vec<_>>();
+        if let Some(invalid) = zones.iter().find(|id| !is_zone_id(id)) {
+            return Err(format!(
+                "Invalid zone ID \"{}\": expected two letters, Z or C, and three digits, such as \"WAZ558\".",
+                invalid
+            ));
+        }
+        Ok(AlertArea::Zones(zones))
+    }
+
+    /// The query parameter selecting this area.
+    pub fn query(&self) -> String {
+        match self {
+            AlertArea::State(code) => format!("area={}", code),
+            AlertArea::MarineArea(area) => format!("area={}", area.as_str()),
+            AlertArea::Region(region) => format!("region={}", region.as_str()),
+            AlertArea::Zones(zones) => format!("zone={}", zones.join(",")),
+        }
+    }
+}
+
+impl std::fmt::Display for AlertArea {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlertArea::State(code) => write!(f, "state {}", code),
+            AlertArea::MarineArea(area) => write!(f, "marine area {}", area.as_str()),
+            AlertArea::Region(region) => write!(f, "region {}", region.as_str()),
+            AlertArea::Zones(zones) => write!(f, "zones {}", zones.join(", ")),
+        }
+    }
+}
+
+/// State, territory and marine area codes whose bounding box comes within
+/// `radius_km` of `point`. Boxes overestimate areas, so this may include an
+/// area the radius doesn't actually reach, but never misses one.
+pub fn areas_near(point: &Coordinates, radius_km: f64) -> Vec<&'static str> {
+    let mut areas: Vec<&'static str> = Vec::new();
+    for &(code, south, north, west, east) in AREA_BOUNDS {
+        let nearest = Coordinates {
+            latitude: point.latitude.clamp(south, north),
+            longitude: point.longitude.clamp(west, east),
+        };
+        if !areas.contains(&code) && point.distance_km(&nearest) <= radius_km {
+            areas.push(code);
+        }
+    }
+    areas
+}
+
+fn is_zone_id(id: &str) -> bool {
+    let bytes = id.as_bytes();
+    bytes.len() == 6
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && matches!(bytes[2], b'Z' | b'C')
+        && bytes[3..].iter().all(u8::is_ascii_digit)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+pub enum Severity {
+    Extreme,
+    Severe,
+    Moderate,
+    Minor,
+    Unknown,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Extreme => "Extreme",
+            Severity::Severe => "Severe",
+            Severity::Moderate => "Moderate",
+            Severity::Minor => "Minor",
+            Severity::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+pub enum Urgency {
+    Immediate,
+    Expected,
+    Future,
+    Past,
+    Unknown,
+}
+
+impl Urgency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Immediate => "Immediate",
+            Urgency::Expected => "Expected",
+            Urgency::Future => "Future",
+            Urgency::Past => "Past",
+            Urgency::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+pub enum Certainty {
+    Observed,
+    Likely,
+    Possible,
+    Unlikely,
+    Unknown,
+}
+
+impl Certainty {
+    fn as_str(self) -> &'static str {
+        match self {
+            Certainty::Observed => "Observed",
+            Certainty::Likely => "Likely",
+            Certainty::Possible => "Possible",
+            Certainty::Unlikely => "Unlikely",
+            Certainty::Unknown => "Unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageType {
+    Alert,
+    Update,
+    Cancel,
+}
+
+impl MessageType {
+    fn as_str(self) -> &'static str {
+        match self {
+            MessageType::Alert => "alert",
+            MessageType::Update => "update",
+            MessageType::Cancel => "cancel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Actual,
+    Exercise,
+    System,
+    Test,
+    Draft,
+}
+
+impl AlertStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            AlertStatus::Actual => "actual",
+            AlertStatus::Exercise => "exercise",
+            AlertStatus::System => "system",
+            AlertStatus::Test => "test",
+            AlertStatus::Draft => "draft",
+        }
+    }
+}
+
+// Optional filters shared by the alert tools; an empty list means no filter.
+// Not a doc comment, which would become the description of every tool schema
+// that flattens these fields in.
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct AlertFilters {
+    #[schemars(description = "only alerts with one of these severities")]
+    #[serde(default)]
+    pub severity: Vec<Severity>,
+    #[schemars(description = "only alerts with one of these urgencies")]
+    #[serde(default)]
+    pub urgency: Vec<Urgency>,
+    #[schemars(description = "only alerts with one of these certainties")]
+    #[serde(default)]
+    pub certainty: Vec<Certainty>,
+    #[schemars(
+        description = "only alerts for these event names, e.g. \"Tornado Warning\" (case-insensitive)"
+    )]
+    #[serde(default)]
+    pub event: Vec<String>,
+    #[schemars(description = "only alerts with one of these message types")]
+    #[serde(default)]
+    pub message_type: Vec<MessageType>,
+    #[schemars(
+        description = "only alerts with one of these statuses; use [\"actual\"] to leave out tests and exercises"
+    )]
+    #[serde(default)]
+    pub status: Vec<AlertStatus>,
+    #[schemars(description = "maximum number of alerts to return")]
+    pub limit: Option<usize>,
+}
+
+impl AlertFilters {
+    /// Query parameters for the filters NWS applies, each starting with `&`.
+    pub fn query(&self) -> String {
+        let mut query = String::new();
+        push_param(
+            &mut query,
+            "severity",
+            self.severity.iter().map(|v| v.as_str()),
+        );
+        push_param(
+            &mut query,
+            "urgency",
+            self.urgency.iter().map(|v| v.as_str()),
+        );
+        push_param(
+            &mut query,
+            "certainty",
+            self.certainty.iter().map(|v| v.as_str()),
+        );
+        push_param(
+            &mut query,
+            "message_type",
+            self.message_type.iter().map(|v| v.as_str()),
+        );
+        push_param(&mut query, "status", self.status.iter().map(|v| v.as_str()));
+        let events: Vec<String> = self.event.iter().map(|event| event_name(event)).collect();
+        push_param(&mut query, "event", events.iter().map(String::as_str));
+        query
+    }
+
+    /// Whether an alert passes the event filter. NWS filters events itself,
+    /// but only by exact name; this catches anything it lets through.
+    pub fn matches(&self, alert: &FeatureProps) -> bool {
+        self.event.is_empty()
+            || self
+                .event
+                .iter()
+                .any(|event| event.trim().eq_ignore_ascii_case(&alert.event))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.limit == Some(0) {
+            return Err("Invalid limit 0: must be at least 1.".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn push_param<'a>(query: &mut String, name: &str, values: impl Iterator<Item = &'a str>) {
+    let values: Vec<_> = values
+        .map(|value| utf8_percent_encode(value, NON_ALPHANUMERIC).to_string())
+        .collect();
+    if !values.is_empty() {
+        query.push_str(&format!("&{}={}", name, values.join(",")));
+    }
+}
+
+/// Event names as NWS spells them, in title case, since its filter is
+/// case-sensitive: "tornado  WARNING" becomes "Tornado Warning".
+fn event_name(event: &str) -> String {
+    event
+        .split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_sends_every_filter_to_nws() {
+        let filters = AlertFilters {
+            severity: vec![Severity::Extreme, Severity::Severe],
+            status: vec![AlertStatus::Actual],
+            event: vec!["tornado  WARNING".to_string(), "Flood Watch".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.query(),
+            "&severity=Extreme,Severe&status=actual&event=Tornado%20Warning,Flood%20Watch"
+        );
+        assert_eq!(AlertFilters::default().query(), "");
+    }
+}
@@ -0,0 +1,643 @@
+//! Bearer-token authentication for the MCP endpoints.
+//!
+//! Clients present either a static API key or a JWT access token issued by an
+//! OAuth 2.1 authorization server. JWTs are verified against keys from a local
+//! JWKS file and must carry the configured issuer and audience. Unauthenticated
+//! requests get a 401 with a `WWW-Authenticate` challenge pointing at the
+//! OAuth protected-resource metadata document (RFC 9728), which tells clients
+//! where to obtain a token.
+
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::{digest, signature};
+
+/// Path of the protected-resource metadata document, before the resource path.
+pub const METADATA_PATH: &str = "/.well-known/oauth-protected-resource";
+
+/// Clock skew tolerated when checking `exp` and `nbf`, in seconds.
+const LEEWAY_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default)]
+pub struct AuthConfig {
+    /// Static bearer tokens accepted as-is.
+    pub api_keys: Vec<String>,
+    /// JSON Web Key Set used to verify JWT signatures.
+    pub jwks_file: Option<PathBuf>,
+    /// Expected `iss` claim, also advertised as the authorization server.
+    pub issuer: Option<String>,
+    /// Expected `aud` claim.
+    pub audience: Option<String>,
+    /// Public URL of the `/mcp` endpoint, if it differs from the bind address.
+    pub resource: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwks_file.is_some()
+    }
+}
+
+/// The authenticated caller, added to the request extensions.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// The JWT `sub` claim, or `api-key:` and a digest prefix for static
+    /// keys.
+    pub subject: String,
+}
+
+#[derive(Debug)]
+pub struct Authenticator {
+    /// SHA-256 digests of the API keys, so that comparisons don't leak the
+    /// keys through timing.
+    api_keys: Vec<digest::Digest>,
+    jwt: Option<JwtValidator>,
+    /// URL of the metadata document, sent in challenges.
+    metadata_url: String,
+    metadata: serde_json::Value,
+}
+
+impl Authenticator {
+    /// Builds the authenticator, or returns `None` when authentication is not
+    /// configured.
+    pub fn new(config: &AuthConfig, bind_address: SocketAddr) -> Result<Option<Arc<Self>>, String> {
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+
+        let jwt = match &config.jwks_file {
+            Some(path) => {
+                let (Some(issuer), Some(audience)) = (&config.issuer, &config.audience) else {
+                    return Err("auth.jwks_file requires auth.issuer and auth.audience".to_string());
+                };
+                let data = std::fs::read(path)
+                    .map_err(|e| format!("Cannot read JWKS file {}: {}", path.display(), e))?;
+                let jwks: Jwks = serde_json::from_slice(&data)
+                    .map_err(|e| format!("Invalid JWKS file {}: {}", path.display(), e))?;
+                tracing::info!(
+                    "Loaded {} signing keys from {}",
+                    jwks.keys.len(),
+                    path.display()
+                );
+                Some(JwtValidator {
+                    keys: jwks.keys,
+                    issuer: issuer.clone(),
+                    audience: audience.clone(),
+                })
+            }
+            None => None,
+        };
+
+        let resource = config
+            .resource
+            .clone()
+            .unwrap_or_else(|| format!("http://{}{}", bind_address, crate::MCP_PATH));
+        let url =
+            reqwest::Url::parse(&resource).map_err(|e| format!("Invalid auth.resource: {e}"))?;
+        let metadata_url = format!(
+            "{}{}{}",
+            url.origin().ascii_serialization(),
+            METADATA_PATH,
+            url.path().trim_end_matches('/')
+        );
+        let metadata = serde_json::json!({
+            "resource": resource,
+            "authorization_servers": config.issuer.iter().collect::<Vec<_>>(),
+            "bearer_methods_supported": ["header"],
+            "resource_name": env!("CARGO_PKG_NAME"),
+        });
+
+        Ok(Some(Arc::new(Self {
+            api_keys: config
+                .api_keys
+                .iter()
+                .map(|key| digest::digest(&digest::SHA256, key.as_bytes()))
+                .collect(),
+            jwt,
+            metadata_url,
+            metadata,
+        })))
+    }
+
+    fn authenticate(&self, token: &str) -> Result<Principal, String> {
+        let token_digest = digest::digest(&digest::SHA256, token.as_bytes());
+        if self
+            .api_keys
+            .iter()
+            .any(|key| key.as_ref() == token_digest.as_ref())
+        {
+            // Tells keys apart in logs and rate limits without revealing them
+            let id: String = token_digest.as_ref()[..4]
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            return Ok(Principal {
+                subject: format!("api-key:{id}"),
+            });
+        }
+
+        match &self.jwt {
+            Some(jwt) if token.split('.').count() == 3 => jwt.validate(token),
+            _ => Err("unknown token".to_string()),
+        }
+    }
+
+    fn challenge(&self, error: Option<&str>) -> Response {
+        let mut challenge = format!("Bearer resource_metadata=\"{}\"", self.metadata_url);
+        let body = match error {
+            Some(description) => {
+                challenge.push_str(&format!(
+                    ", error=\"invalid_token\", error_description=\"{}\"",
+                    description.replace('"', "'")
+                ));
+                serde_json::json!({ "error": "invalid_token", "error_description": description })
+            }
+            None => {
+                serde_json::json!({ "error": "unauthorized", "error_description": "Bearer token required" })
+            }
+        };
+
+        let mut response = (StatusCode::UNAUTHORIZED, Json(body)).into_response();
+        if let Ok(value) = HeaderValue::from_str(&challenge) {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, value);
+        }
+        response
+    }
+}
+
+/// Axum middleware requiring a valid bearer token.
+pub async fn require_bearer(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            let (scheme, token) = v.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+        });
+    let Some(token) = token else {
+        return auth.challenge(None);
+    };
+
+    match auth.authenticate(token) {
+        Ok(principal) => {
+            tracing::debug!(subject = principal.subject, "Authenticated request");
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Err(e) => {
+            tracing::info!("Rejected bearer token: {}", e);
+            auth.challenge(Some(&e))
+        }
+    }
+}
+
+/// Serves the OAuth protected-resource metadata document.
+pub async fn protected_resource_metadata(
+    State(auth): State<Arc<Authenticator>>,
+) -> Json<serde_json::Value> {
+    Json(auth.metadata.clone())
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    #[serde(rename = "use")]
+    key_use: Option<String>,
+    // RSA
+    n: Option<String>,
+    e: Option<String>,
+    // EC and OKP
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct Claims {
+    iss: Option<String>,
+    sub: Option<String>,
+    aud: Option<Audience>,
+    exp: Option<u64>,
+    nbf: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == audience,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == audience),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct JwtValidator {
+    keys: Vec<Jwk>,
+    issuer: String,
+    audience: String,
+}
+
+impl JwtValidator {
+    fn validate(&self, token: &str) -> Result<Principal, String> {
+        // The signature covers `header.payload` as sent
+        let (message, sig) = token.rsplit_once('.').ok_or("malformed token")?;
+        let (header, payload) = message.split_once('.').ok_or("malformed token")?;
+        let header: JwtHeader = decode_json(header)?;
+        let claims: Claims = decode_json(payload)?;
+        let sig = URL_SAFE_NO_PAD
+            .decode(sig)
+            .map_err(|_| "malformed signature".to_string())?;
+
+        let verified = self
+            .keys
+            .iter()
+            .filter(|key| key.key_use.as_deref().is_none_or(|u| u == "sig"))
+            .filter(|key| header.kid.is_none() || key.kid == header.kid)
+            .filter(|key| key.alg.as_deref().is_none_or(|alg| alg == header.alg))
+            .any(|key| verify(key, &header.alg, message.as_bytes(), &sig));
+        if !verified {
+            return Err("signature verification failed".to_string());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match claims.exp {
+            Some(exp) if exp.saturating_add(LEEWAY_SECS) > now => {}
+            Some(_) => return Err("token expired".to_string()),
+            None => return Err("token has no expiry".to_string()),
+        }
+        if claims.nbf.is_some_and(|nbf| nbf > now + LEEWAY_SECS) {
+            return Err("token not yet valid".to_string());
+        }
+        if claims.iss.as_deref() != Some(self.issuer.as_str()) {
+            return Err("unexpected issuer".to_string());
+        }
+        if !claims.aud.is_some_and(|aud| aud.contains(&self.audience)) {
+            return Err("token not issued for this resource".to_string());
+        }
+
+        Ok(Principal {
+            subject: claims.sub.unwrap_or_default(),
+        })
+    }
+}
+
+fn decode_json<T: serde::de::DeserializeOwned>(segment: &str) -> Result<T, String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|_| "malformed token".to_string())?;
+    serde_json::from_slice(&bytes).map_err(|_| "malformed token".to_string())
+}
+
+/// Verifies a JWS signature with one key. Unsupported algorithms and key types
+/// never verify; in particular `none` is rejected.
+fn verify(key: &Jwk, alg: &str, message: &[u8], sig: &[u8]) -> bool {
+    let decode = |field: &Option<String>| {
+        field
+            .as_deref()
+            .and_then(|v| URL_SAFE_NO_PAD.decode(v).ok())
+    };
+
+    match (key.kty.as_str(), alg) {
+        ("RSA", "RS256" | "RS384" | "RS512" | "PS256" | "PS384" | "PS512") => {
+            let (Some(n), Some(e)) = (decode(&key.n), decode(&key.e)) else {
+                return false;
+            };
+            let params = match alg {
+                "RS256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+                "RS384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+                "RS512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+                "PS256" => &signature::RSA_PSS_2048_8192_SHA256,
+                "PS384" => &signature::RSA_PSS_2048_8192_SHA384,
+                _ => &signature::RSA_PSS_2048_8192_SHA512,
+            };
+            signature::RsaPublicKeyComponents { n, e }
+                .verify(params, message, sig)
+                .is_ok()
+        }
+        ("EC", "ES256" | "ES384") => {
+            let (Some(x), Some(y)) = (decode(&key.x), decode(&key.y)) else {
+                return false;
+            };
+            let (crv, algorithm) = match alg {
+                "ES256" => ("P-256", &signature::ECDSA_P256_SHA256_FIXED),
+                _ => ("P-384", &signature::ECDSA_P384_SHA384_FIXED),
+            };
+            if key.crv.as_deref() != Some(crv) {
+                return false;
+            }
+            // Uncompressed SEC1 point
+            let point = [&[0x04][..], &x, &y].concat();
+            signature::UnparsedPublicKey::new(algorithm, point)
+                .verify(message, sig)
+                .is_ok()
+        }
+        ("OKP", "EdDSA") if key.crv.as_deref() == Some("Ed25519") => {
+            let Some(x) = decode(&key.x) else {
+                return false;
+            };
+            signature::UnparsedPublicKey::new(&signature::ED25519, x)
+                .verify(message, sig)
+                .is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, Ed25519KeyPair, KeyPair},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    const ISSUER: &str = "https://auth.example.com";
+    const AUDIENCE: &str = "https://weather.example.com/mcp";
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn ed25519_key() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    fn ed25519_jwk(key: &Ed25519KeyPair, kid: &str) -> serde_json::Value {
+        json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "kid": kid,
+            "x": URL_SAFE_NO_PAD.encode(key.public_key().as_ref()),
+        })
+    }
+
+    /// An authenticator accepting `api_keys` and tokens signed by `keys`.
+    fn authenticator(api_keys: &[&str], keys: Vec<serde_json::Value>) -> Arc<Authenticator> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "mcp-weather-jwks-{}-{}.json",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, json!({ "keys": keys }).to_string()).unwrap();
+
+        let config = AuthConfig {
+            api_keys: api_keys.iter().map(|key| key.to_string()).collect(),
+            jwks_file: Some(path.clone()),
+            issuer: Some(ISSUER.to_string()),
+            audience: Some(AUDIENCE.to_string()),
+            resource: None,
+        };
+        let auth = Authenticator::new(&config, SocketAddr::from(([127, 0, 0, 1], 8000)));
+        std::fs::remove_file(path).unwrap();
+        auth.unwrap().unwrap()
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "user-1",
+            "aud": AUDIENCE,
+            "exp": now() + 300,
+        })
+    }
+
+    fn encode(value: &serde_json::Value) -> String {
+        URL_SAFE_NO_PAD.encode(value.to_string())
+    }
+
+    /// `header.payload`, ready to be signed.
+    fn message(header: serde_json::Value, claims: &serde_json::Value) -> String {
+        format!("{}.{}", encode(&header), encode(claims))
+    }
+
+    fn sign_ed25519(key: &Ed25519KeyPair, kid: &str, claims: &serde_json::Value) -> String {
+        let message = message(json!({ "alg": "EdDSA", "kid": kid }), claims);
+        let sig = key.sign(message.as_bytes());
+        format!("{}.{}", message, URL_SAFE_NO_PAD.encode(sig.as_ref()))
+    }
+
+    #[test]
+    fn accepts_valid_eddsa_token() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        let principal = auth
+            .authenticate(&sign_ed25519(&key, "k1", &claims()))
+            .unwrap();
+        assert_eq!(principal.subject, "user-1");
+    }
+
+    #[test]
+    fn accepts_valid_es256_token() {
+        let rng = SystemRandom::new();
+        let algorithm = &signature::ECDSA_P256_SHA256_FIXED_SIGNING;
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(algorithm, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(algorithm, pkcs8.as_ref(), &rng).unwrap();
+        // Uncompressed SEC1 point: 0x04, x, y
+        let point = key.public_key().as_ref();
+        let auth = authenticator(
+            &[],
+            vec![json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": "ec",
+                "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+            })],
+        );
+
+        let message = message(json!({ "alg": "ES256", "kid": "ec" }), &claims());
+        let sig = key.sign(&rng, message.as_bytes()).unwrap();
+        let token = format!("{}.{}", message, URL_SAFE_NO_PAD.encode(sig.as_ref()));
+        assert_eq!(auth.authenticate(&token).unwrap().subject, "user-1");
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        // Signed by a key the server doesn't know
+        let token = sign_ed25519(&ed25519_key(), "k1", &claims());
+        assert!(auth.authenticate(&token).is_err());
+
+        // Claims changed after signing
+        let token = sign_ed25519(&key, "k1", &claims());
+        let (_, sig) = token.rsplit_once('.').unwrap();
+        let mut tampered = claims();
+        tampered["sub"] = json!("admin");
+        let forged = format!(
+            "{}.{}",
+            message(json!({ "alg": "EdDSA", "kid": "k1" }), &tampered),
+            sig
+        );
+        assert!(auth.authenticate(&forged).is_err());
+    }
+
+    #[test]
+    fn rejects_alg_none() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        for header in [
+            json!({ "alg": "none" }),
+            json!({ "alg": "none", "kid": "k1" }),
+        ] {
+            let token = format!("{}.", message(header, &claims()));
+            assert!(auth.authenticate(&token).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_kid() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        let token = sign_ed25519(&key, "k2", &claims());
+        assert_eq!(
+            auth.authenticate(&token).unwrap_err(),
+            "signature verification failed"
+        );
+    }
+
+    #[test]
+    fn checks_expiry() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        let mut expired = claims();
+        expired["exp"] = json!(now() - LEEWAY_SECS - 10);
+        let token = sign_ed25519(&key, "k1", &expired);
+        assert_eq!(auth.authenticate(&token).unwrap_err(), "token expired");
+
+        // Within the leeway
+        let mut recent = claims();
+        recent["exp"] = json!(now() - 10);
+        assert!(
+            auth.authenticate(&sign_ed25519(&key, "k1", &recent))
+                .is_ok()
+        );
+
+        // Must not overflow
+        let mut far = claims();
+        far["exp"] = json!(u64::MAX);
+        assert!(auth.authenticate(&sign_ed25519(&key, "k1", &far)).is_ok());
+
+        let mut missing = claims();
+        missing.as_object_mut().unwrap().remove("exp");
+        let token = sign_ed25519(&key, "k1", &missing);
+        assert_eq!(
+            auth.authenticate(&token).unwrap_err(),
+            "token has no expiry"
+        );
+    }
+
+    #[test]
+    fn rejects_token_not_yet_valid() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        let mut early = claims();
+        early["nbf"] = json!(now() + LEEWAY_SECS + 60);
+        let token = sign_ed25519(&key, "k1", &early);
+        assert_eq!(
+            auth.authenticate(&token).unwrap_err(),
+            "token not yet valid"
+        );
+    }
+
+    #[test]
+    fn checks_issuer_and_audience() {
+        let key = ed25519_key();
+        let auth = authenticator(&[], vec![ed25519_jwk(&key, "k1")]);
+
+        let mut wrong_issuer = claims();
+        wrong_issuer["iss"] = json!("https://evil.example.com");
+        let token = sign_ed25519(&key, "k1", &wrong_issuer);
+        assert_eq!(auth.authenticate(&token).unwrap_err(), "unexpected issuer");
+
+        let mut wrong_audience = claims();
+        wrong_audience["aud"] = json!("https://other.example.com/mcp");
+        let token = sign_ed25519(&key, "k1", &wrong_audience);
+        assert_eq!(
+            auth.authenticate(&token).unwrap_err(),
+            "token not issued for this resource"
+        );
+
+        let mut audiences = claims();
+        audiences["aud"] = json!(["https://other.example.com/mcp", AUDIENCE]);
+        assert!(
+            auth.authenticate(&sign_ed25519(&key, "k1", &audiences))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn accepts_api_keys() {
+        let auth = authenticator(&["secret-one", "secret-two"], Vec::new());
+
+        let principal = auth.authenticate("secret-two").unwrap();
+        assert!(principal.subject.starts_with("api-key:"));
+        assert!(!principal.subject.contains("secret"));
+        assert_ne!(
+            auth.authenticate("secret-one").unwrap().subject,
+            principal.subject
+        );
+
+        assert_eq!(
+            auth.authenticate("secret-three").unwrap_err(),
+            "unknown token"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! In-memory HTTP response cache for NWS requests.
+//!
+//! Entries are keyed by URL and kept fresh according to `Cache-Control:
+//! max-age` (less any `Age`) or `Expires`. Stale entries that carry an `ETag`
+//! or `Last-Modified` validator are revalidated with a conditional request
+//! instead of being fetched again. The cache is bounded by total body size and
+//! evicts the least recently used entries first. Expired entries are kept
+//! until evicted so they can be served as a last resort when NWS is down.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use reqwest::header::{self, HeaderMap};
+
+#[derive(Debug)]
+struct Entry {
+    body: Bytes,
+    validators: Validators,
+    /// When the body was last fetched or revalidated.
+    stored_at: Instant,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+/// Conditional request headers for revalidating a stale entry.
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: get(header::ETAG),
+            last_modified: get(header::LAST_MODIFIED),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Adds `If-None-Match` / `If-Modified-Since` to a request.
+    pub fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+pub enum Lookup {
+    /// A fresh copy is cached; no request is needed.
+    Fresh(Bytes),
+    /// A stale copy is cached and may be revalidated.
+    Stale(Validators),
+    Miss,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    size: usize,
+    clock: u64,
+}
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+    max_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+}
+
+impl ResponseCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            entries: Mutex::default(),
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn lookup(&self, url: &str) -> Lookup {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+
+        let lookup = match entries.map.get_mut(url) {
+            Some(entry) => {
+                entry.last_used = clock;
+                if entry.expires_at > Instant::now() {
+                    Lookup::Fresh(entry.body.clone())
+                } else if !entry.validators.is_empty() {
+                    Lookup::Stale(entry.validators.clone())
+                } else {
+                    Lookup::Miss
+                }
+            }
+            None => Lookup::Miss,
+        };
+        drop(entries);
+
+        match &lookup {
+            Lookup::Fresh(_) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(url, hits = self.hits(), misses = self.misses(), "Cache hit");
+            }
+            Lookup::Stale(_) | Lookup::Miss => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(
+                    url,
+                    hits = self.hits(),
+                    misses = self.misses(),
+                    "Cache miss"
+                );
+            }
+        }
+        lookup
+    }
+
+    /// Stores a 200 response if its headers allow caching.
+    pub fn store(&self, url: &str, headers: &HeaderMap, body: Bytes) {
+        let Some(ttl) = freshness_lifetime(headers) else {
+            return;
+        };
+        let validators = Validators::from_headers(headers);
+        if ttl.is_zero() && validators.is_empty() {
+            return;
+        }
+        if body.len() > self.max_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let now = Instant::now();
+        let entry = Entry {
+            body,
+            validators,
+            stored_at: now,
+            expires_at: now + ttl,
+            last_used: entries.clock,
+        };
+        entries.size += entry.body.len();
+        if let Some(previous) = entries.map.insert(url.to_string(), entry) {
+            entries.size -= previous.body.len();
+        }
+
+        // Evict least recently used entries until back under the limit
+        while entries.size > self.max_bytes {
+            let Some(oldest) = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = entries.map.remove(&oldest) {
+                entries.size -= evicted.body.len();
+                tracing::debug!(url = oldest, "Evicted from response cache");
+            }
+        }
+    }
+
+    /// Handles a 304 Not Modified response by refreshing the cached entry's
+    /// lifetime and returning its body.
+    pub fn revalidated(&self, url: &str, headers: &HeaderMap) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.map.get_mut(url)?;
+        entry.stored_at = Instant::now();
+        entry.expires_at = entry.stored_at + freshness_lifetime(headers).unwrap_or_default();
+        let updated = Validators::from_headers(headers);
+        if !updated.is_empty() {
+            entry.validators = updated;
+        }
+        let body = entry.body.clone();
+        drop(entries);
+
+        self.revalidations.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(
+            url,
+            revalidations = self.revalidations(),
+            "Cache entry revalidated"
+        );
+        Some(body)
+    }
+
+    /// Returns a cached body regardless of freshness, with its age, if it is
+    /// no older than `max_age`.
+    pub fn last_known_good(&self, url: &str, max_age: Duration) -> Option<(Bytes, Duration)> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.map.get(url)?;
+        let age = entry.stored_at.elapsed();
+        (age <= max_age).then(|| (entry.body.clone(), age))
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn revalidations(&self) -> u64 {
+        self.revalidations.load(Ordering::Relaxed)
+    }
+
+    /// Total size of the cached bodies in bytes.
+    pub fn size(&self) -> usize {
+        self.entries.lock().unwrap().size
+    }
+}
+
+/// How long a response may be served without revalidation, or `None` if it
+/// must not be stored at all.
+fn freshness_lifetime(headers: &HeaderMap) -> Option<Duration> {
+    let cache_control = headers
+        .get_all(header::CACHE_CONTROL)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+
+    if cache_control
+        .iter()
+        .any(|d| d == "no-store" || d == "private")
+    {
+        return None;
+    }
+    if cache_control.iter().any(|d| d == "no-cache") {
+        return Some(Duration::ZERO);
+    }
+
+    let max_age = cache_control
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age=")?.parse::<u64>().ok());
+    if let Some(max_age) = max_age {
+        let age = headers
+            .get(header::AGE)
+            .and_then(|v| v.to_str().ok()?.parse::<u64>().ok())
+            .unwrap_or(0);
+        return Some(Duration::from_secs(max_age.saturating_sub(age)));
+    }
+
+    let expires = headers
+        .get(header::EXPIRES)
+        .and_then(|v| httpdate::parse_http_date(v.to_str().ok()?).ok());
+    Some(
+        expires
+            .and_then(|expires| expires.duration_since(SystemTime::now()).ok())
+            .unwrap_or_default(),
+    )
+}
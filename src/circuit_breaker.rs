@@ -0,0 +1,133 @@
+//! Circuit breaker for the NWS upstream.
+//!
+//! After `failure_threshold` consecutive upstream failures the circuit opens
+//! and requests fail immediately for `open_duration`. Once that elapses a
+//! single probe request is let through: success closes the circuit, failure
+//! opens it again.
+
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { since: Instant },
+}
+
+/// Externally visible state, for health reporting.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitStatus {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+    failure_threshold: u32,
+    open_duration: Duration,
+    opened: AtomicU64,
+    rejections: AtomicU64,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            state: Mutex::new(State::Closed { failures: 0 }),
+            failure_threshold,
+            open_duration,
+            opened: AtomicU64::new(0),
+            rejections: AtomicU64::new(0),
+        }
+    }
+
+    /// Asks permission to send a request. Returns how long until the circuit
+    /// may close again if the request must be rejected.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } => {
+                let now = Instant::now();
+                if now >= until {
+                    tracing::info!("Circuit half-open; sending probe request");
+                    *state = State::HalfOpen { since: now };
+                    Ok(())
+                } else {
+                    self.rejections.fetch_add(1, Ordering::Relaxed);
+                    Err(until - now)
+                }
+            }
+            // A probe is already in flight, unless it was abandoned without
+            // reporting back
+            State::HalfOpen { since } => {
+                if since.elapsed() >= self.open_duration {
+                    *state = State::HalfOpen {
+                        since: Instant::now(),
+                    };
+                    Ok(())
+                } else {
+                    self.rejections.fetch_add(1, Ordering::Relaxed);
+                    Err(self.open_duration.saturating_sub(since.elapsed()))
+                }
+            }
+        }
+    }
+
+    pub fn status(&self) -> CircuitStatus {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => CircuitStatus::Closed,
+            State::Open { until } if Instant::now() < until => CircuitStatus::Open,
+            // The next request will be let through as a probe
+            State::Open { .. } | State::HalfOpen { .. } => CircuitStatus::HalfOpen,
+        }
+    }
+
+    /// Times the circuit has opened.
+    pub fn opened(&self) -> u64 {
+        self.opened.load(Ordering::Relaxed)
+    }
+
+    /// Requests rejected while the circuit was open or a probe was in flight.
+    pub fn rejections(&self) -> u64 {
+        self.rejections.load(Ordering::Relaxed)
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(*state, State::HalfOpen { .. }) {
+            tracing::info!("Circuit closed; NWS upstream recovered");
+        }
+        *state = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            State::HalfOpen { .. } => self.failure_threshold,
+            State::Open { .. } => return,
+        };
+        if failures >= self.failure_threshold {
+            tracing::warn!(
+                "Circuit opened after {} consecutive failures; pausing NWS requests for {:?}",
+                failures,
+                self.open_duration
+            );
+            *state = State::Open {
+                until: Instant::now() + self.open_duration,
+            };
+            self.opened.fetch_add(1, Ordering::Relaxed);
+        } else {
+            *state = State::Closed { failures };
+        }
+    }
+}
@@ -0,0 +1,793 @@
+//! Server configuration from command-line flags, environment variables and a
+//! TOML config file.
+//!
+//! Each setting can come from any of the three sources. Precedence, highest
+//! first: command-line flag, environment variable, config file, built-in
+//! default. The config file is read from `--config <path>` or
+//! `MCP_WEATHER_CONFIG`; keys in a `[section]` are addressed as
+//! `section.key`.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{auth::AuthConfig, rate_limit::Rate, retry::RetryPolicy};
+
+struct Setting {
+    /// Key in the config file, `section.key` for keys inside a table.
+    key: &'static str,
+    flag: &'static str,
+    env: &'static str,
+    help: &'static str,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "nws_api_base",
+        flag: "--nws-api-base",
+        env: "MCP_WEATHER_NWS_API_BASE",
+        help: "Base URL of the NWS API [default: https://api.weather.gov]",
+    },
+    Setting {
+        key: "user_agent",
+        flag: "--user-agent",
+        env: "MCP_WEATHER_USER_AGENT",
+        help: "User-Agent sent to NWS; include contact information [default: weather-app/2.0]",
+    },
+    Setting {
+        key: "bind_address",
+        flag: "--bind",
+        env: "MCP_WEATHER_BIND_ADDRESS",
+        help: "Address the HTTP server listens on [default: 127.0.0.1:8000]",
+    },
+    Setting {
+        key: "transport",
+        flag: "--transport",
+        env: "MCP_WEATHER_TRANSPORT",
+        help: "How clients connect: stdio, http (streamable HTTP plus legacy HTTP+SSE) or sse (legacy HTTP+SSE only) [default: http]",
+    },
+    Setting {
+        key: "max_sessions",
+        flag: "--max-sessions",
+        env: "MCP_WEATHER_MAX_SESSIONS",
+        help: "Concurrent HTTP sessions allowed across /mcp and /sse [default: 100]",
+    },
+    Setting {
+        key: "session_idle_timeout_secs",
+        flag: "--session-idle-timeout",
+        env: "MCP_WEATHER_SESSION_IDLE_TIMEOUT",
+        help: "Close a streamable HTTP session after this many seconds without a request [default: 1800]",
+    },
+    Setting {
+        key: "auth.api_keys",
+        flag: "--api-keys",
+        env: "MCP_WEATHER_API_KEYS",
+        help: "Comma-separated static bearer tokens accepted on the HTTP endpoints",
+    },
+    Setting {
+        key: "auth.jwks_file",
+        flag: "--auth-jwks-file",
+        env: "MCP_WEATHER_AUTH_JWKS_FILE",
+        help: "JWKS file with the keys that sign accepted JWT access tokens",
+    },
+    Setting {
+        key: "auth.issuer",
+        flag: "--auth-issuer",
+        env: "MCP_WEATHER_AUTH_ISSUER",
+        help: "Required `iss` of JWT access tokens; advertised as the authorization server",
+    },
+    Setting {
+        key: "auth.audience",
+        flag: "--auth-audience",
+        env: "MCP_WEATHER_AUTH_AUDIENCE",
+        help: "Required `aud` of JWT access tokens",
+    },
+    Setting {
+        key: "auth.resource",
+        flag: "--auth-resource",
+        env: "MCP_WEATHER_AUTH_RESOURCE",
+        help: "Public URL of the /mcp endpoint [default: http://<bind>/mcp]",
+    },
+    Setting {
+        key: "rate_limit.requests_per_minute",
+        flag: "--rate-limit",
+        env: "MCP_WEATHER_RATE_LIMIT",
+        help: "HTTP requests per minute allowed per client; 0 disables [default: 120]",
+    },
+    Setting {
+        key: "rate_limit.burst",
+        flag: "--rate-limit-burst",
+        env: "MCP_WEATHER_RATE_LIMIT_BURST",
+        help: "Requests a client may send in a burst above the rate [default: 30]",
+    },
+    Setting {
+        key: "readiness.check_upstream",
+        flag: "--readiness-check-upstream",
+        env: "MCP_WEATHER_READINESS_CHECK_UPSTREAM",
+        help: "Make /readyz also check that the NWS API answers (true or false) [default: false]",
+    },
+    Setting {
+        key: "otlp.endpoint",
+        flag: "--otlp-endpoint",
+        env: "MCP_WEATHER_OTLP_ENDPOINT",
+        help: "OTLP/HTTP collector to export traces to, e.g. http://127.0.0.1:4318 [default: disabled]",
+    },
+    Setting {
+        key: "log.format",
+        flag: "--log-format",
+        env: "MCP_WEATHER_LOG_FORMAT",
+        help: "Log output: text or json (one object per line) [default: text]",
+    },
+    Setting {
+        key: "log.level",
+        flag: "--log-level",
+        env: "MCP_WEATHER_LOG_LEVEL",
+        help: "Log filter such as info or mcp_weather_rust=debug; RUST_LOG overrides it [default: info]",
+    },
+    Setting {
+        key: "log.redact_coordinates",
+        flag: "--log-redact-coordinates",
+        env: "MCP_WEATHER_LOG_REDACT_COORDINATES",
+        help: "Truncate coordinates in logs and exported traces to one decimal place (true or false) [default: false]",
+    },
+    Setting {
+        key: "data_dir",
+        flag: "--data-dir",
+        env: "MCP_WEATHER_DATA_DIR",
+        help: "Directory for persistent data [default: $XDG_DATA_HOME/mcp-weather-rust]",
+    },
+    Setting {
+        key: "cache.max_mb",
+        flag: "--cache-max-mb",
+        env: "MCP_WEATHER_CACHE_MAX_MB",
+        help: "Size limit of the in-memory response cache in MiB [default: 32]",
+    },
+    Setting {
+        key: "upstream.timeout_secs",
+        flag: "--request-timeout",
+        env: "MCP_WEATHER_REQUEST_TIMEOUT",
+        help: "Timeout for a single NWS request in seconds [default: 30]",
+    },
+    Setting {
+        key: "upstream.requests_per_second",
+        flag: "--upstream-rate",
+        env: "MCP_WEATHER_UPSTREAM_RATE",
+        help: "NWS requests per second across all clients; 0 disables [default: 5]",
+    },
+    Setting {
+        key: "upstream.max_queue_secs",
+        flag: "--upstream-max-queue",
+        env: "MCP_WEATHER_UPSTREAM_MAX_QUEUE",
+        help: "Longest a request waits for the NWS budget before failing [default: 5]",
+    },
+    Setting {
+        key: "upstream.max_attempts",
+        flag: "--retry-max-attempts",
+        env: "MCP_WEATHER_RETRY_MAX_ATTEMPTS",
+        help: "Attempts per NWS request, including the first [default: 3]",
+    },
+    Setting {
+        key: "upstream.retry_deadline_secs",
+        flag: "--retry-deadline",
+        env: "MCP_WEATHER_RETRY_DEADLINE",
+        help: "Give up retrying after this many seconds [default: 60]",
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    /// JSON-RPC over stdin/stdout, for clients that launch the server.
+    Stdio,
+    /// Streamable HTTP on `/mcp`.
+    Http,
+    /// Legacy HTTP+SSE on `/sse` and `/message`.
+    Sse,
+}
+
+impl FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "stdio" => Ok(Transport::Stdio),
+            "http" => Ok(Transport::Http),
+            "sse" => Ok(Transport::Sse),
+            _ => Err("expected one of stdio, http, sse".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected text or json".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Without a trailing slash.
+    pub nws_api_base: String,
+    pub user_agent: String,
+    pub bind_address: SocketAddr,
+    pub transport: Transport,
+    pub max_sessions: usize,
+    /// Streamable HTTP sessions without a request for this long are closed,
+    /// freeing their slot under `max_sessions`.
+    pub session_idle_timeout: Duration,
+    pub auth: AuthConfig,
+    pub client_rate: Rate,
+    pub readiness_check_upstream: bool,
+    /// Base URL of the OTLP/HTTP collector; tracing export is off if unset.
+    pub otlp_endpoint: Option<String>,
+    pub log_format: LogFormat,
+    /// `EnvFilter` directives, used when `RUST_LOG` is not set.
+    pub log_level: String,
+    pub log_redact_coordinates: bool,
+    pub data_dir: PathBuf,
+    pub cache_max_bytes: usize,
+    pub request_timeout: Duration,
+    pub upstream_rate: Rate,
+    pub upstream_max_queue: Duration,
+    pub retry: RetryPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            nws_api_base: "https://api.weather.gov".to_string(),
+            user_agent: "weather-app/2.0".to_string(),
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            transport: Transport::Http,
+            max_sessions: 100,
+            session_idle_timeout: Duration::from_secs(30 * 60),
+            auth: AuthConfig::default(),
+            client_rate: Rate {
+                per_second: 2.0,
+                burst: 30,
+            },
+            readiness_check_upstream: false,
+            otlp_endpoint: None,
+            log_format: LogFormat::Text,
+            log_level: "info".to_string(),
+            log_redact_coordinates: false,
+            data_dir: default_data_dir(),
+            cache_max_bytes: 32 * 1024 * 1024,
+            request_timeout: Duration::from_secs(30),
+            upstream_rate: Rate {
+                per_second: 5.0,
+                burst: 5,
+            },
+            upstream_max_queue: Duration::from_secs(5),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// A raw setting value and where it came from, for error messages.
+struct Value {
+    raw: String,
+    source: String,
+}
+
+impl Config {
+    /// Loads the configuration from the process arguments and environment.
+    /// Prints usage and exits for `--help` and `--version`.
+    pub fn load() -> Result<Self, String> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let env: HashMap<String, String> = std::env::vars().collect();
+        Self::from_sources(&args, &env)
+    }
+
+    fn from_sources(args: &[String], env: &HashMap<String, String>) -> Result<Self, String> {
+        let (config_path, cli) = parse_args(args)?;
+
+        let mut values = HashMap::new();
+        let config_path = config_path.or_else(|| env.get("MCP_WEATHER_CONFIG").map(PathBuf::from));
+        if let Some(path) = config_path {
+            values.extend(read_file(&path)?);
+        }
+        for setting in SETTINGS {
+            if let Some(raw) = env.get(setting.env) {
+                values.insert(
+                    setting.key,
+                    Value {
+                        raw: raw.clone(),
+                        source: setting.env.to_string(),
+                    },
+                );
+            }
+        }
+        values.extend(cli);
+
+        let mut config = Config::default();
+        for (key, value) in &values {
+            config.apply(key, value).map_err(|e| {
+                format!(
+                    "Invalid value '{}' for {} (from {}): {}",
+                    value.raw, key, value.source, e
+                )
+            })?;
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        let raw = value.raw.trim();
+        match key {
+            "nws_api_base" => {
+                let url = reqwest::Url::parse(raw).map_err(|e| e.to_string())?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err("must be an http or https URL".to_string());
+                }
+                self.nws_api_base = raw.trim_end_matches('/').to_string();
+            }
+            "user_agent" => {
+                if raw.is_empty() {
+                    return Err("must not be empty".to_string());
+                }
+                self.user_agent = raw.to_string();
+            }
+            "bind_address" => self.bind_address = parse(raw)?,
+            "transport" => self.transport = parse(raw)?,
+            "max_sessions" => {
+                self.max_sessions = parse(raw)?;
+                if self.max_sessions == 0 {
+                    return Err("must be at least 1".to_string());
+                }
+            }
+            "session_idle_timeout_secs" => self.session_idle_timeout = parse_secs(raw)?,
+            "auth.api_keys" => {
+                self.auth.api_keys = raw
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            "auth.jwks_file" => self.auth.jwks_file = Some(PathBuf::from(raw)),
+            "auth.issuer" => self.auth.issuer = Some(raw.to_string()),
+            "auth.audience" => self.auth.audience = Some(raw.to_string()),
+            "auth.resource" => {
+                let url = reqwest::Url::parse(raw).map_err(|e| e.to_string())?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err("must be an http or https URL".to_string());
+                }
+                self.auth.resource = Some(raw.to_string());
+            }
+            "rate_limit.requests_per_minute" => {
+                self.client_rate.per_second = parse_rate(raw)? / 60.0;
+            }
+            "rate_limit.burst" => {
+                self.client_rate.burst = parse(raw)?;
+                if self.client_rate.burst == 0 {
+                    return Err("must be at least 1".to_string());
+                }
+            }
+            "readiness.check_upstream" => self.readiness_check_upstream = parse(raw)?,
+            "otlp.endpoint" => {
+                let url = reqwest::Url::parse(raw).map_err(|e| e.to_string())?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err("must be an http or https URL".to_string());
+                }
+                self.otlp_endpoint = Some(raw.to_string());
+            }
+            "log.format" => self.log_format = parse(raw)?,
+            "log.level" => {
+                tracing_subscriber::EnvFilter::try_new(raw).map_err(|e| e.to_string())?;
+                self.log_level = raw.to_string();
+            }
+            "log.redact_coordinates" => self.log_redact_coordinates = parse(raw)?,
+            "data_dir" => self.data_dir = PathBuf::from(raw),
+            "cache.max_mb" => {
+                self.cache_max_bytes = parse::<usize>(raw)?
+                    .checked_mul(1024 * 1024)
+                    .ok_or("too large for this platform")?;
+            }
+            "upstream.timeout_secs" => self.request_timeout = parse_secs(raw)?,
+            "upstream.requests_per_second" => {
+                let per_second = parse_rate(raw)?;
+                // Allow up to one second's worth of requests at once
+                self.upstream_rate = Rate {
+                    per_second,
+                    burst: per_second.ceil().max(1.0) as u32,
+                };
+            }
+            "upstream.max_queue_secs" => {
+                let secs = parse::<f64>(raw)?;
+                if !secs.is_finite() || secs < 0.0 {
+                    return Err("must be zero or a positive number of seconds".to_string());
+                }
+                self.upstream_max_queue = Duration::from_secs_f64(secs);
+            }
+            "upstream.max_attempts" => {
+                self.retry.max_attempts = parse(raw)?;
+                if self.retry.max_attempts == 0 {
+                    return Err("must be at least 1".to_string());
+                }
+            }
+            "upstream.retry_deadline_secs" => self.retry.deadline = parse_secs(raw)?,
+            _ => return Err("unknown setting".to_string()),
+        }
+        Ok(())
+    }
+}
+
+fn parse<T>(raw: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    raw.parse::<T>().map_err(|e| e.to_string())
+}
+
+fn parse_secs(raw: &str) -> Result<Duration, String> {
+    let secs = parse::<f64>(raw)?;
+    if !secs.is_finite() || secs <= 0.0 {
+        return Err("must be a positive number of seconds".to_string());
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn parse_rate(raw: &str) -> Result<f64, String> {
+    let rate = parse::<f64>(raw)?;
+    if !rate.is_finite() || rate < 0.0 {
+        return Err("must be zero or a positive number".to_string());
+    }
+    Ok(rate)
+}
+
+/// Splits the command line into the config file path and setting values.
+fn parse_args(args: &[String]) -> Result<(Option<PathBuf>, HashMap<&'static str, Value>), String> {
+    let mut config_path = None;
+    let mut values = HashMap::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        match flag {
+            "-h" | "--help" => {
+                println!("{}", usage());
+                std::process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                std::process::exit(0);
+            }
+            _ => {}
+        }
+
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or_else(|| format!("Missing value for {}", flag))
+        };
+        if flag == "--config" {
+            config_path = Some(PathBuf::from(value()?));
+            continue;
+        }
+        let setting = SETTINGS
+            .iter()
+            .find(|setting| setting.flag == flag)
+            .ok_or_else(|| format!("Unknown argument '{}'\n\n{}", arg, usage()))?;
+        values.insert(
+            setting.key,
+            Value {
+                raw: value()?,
+                source: setting.flag.to_string(),
+            },
+        );
+    }
+
+    Ok((config_path, values))
+}
+
+fn usage() -> String {
+    let mut usage = format!(
+        "Usage: {} [OPTIONS]\n\nOptions:\n  --config <PATH>\n      TOML config file [env: MCP_WEATHER_CONFIG]\n",
+        env!("CARGO_PKG_NAME")
+    );
+    for setting in SETTINGS {
+        usage.push_str(&format!(
+            "  {} <VALUE>\n      {} [env: {}] [file: {}]\n",
+            setting.flag, setting.help, setting.env, setting.key
+        ));
+    }
+    usage.push_str("  -h, --help\n  -V, --version\n");
+    usage
+}
+
+fn read_file(path: &Path) -> Result<HashMap<&'static str, Value>, String> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read config file {}: {}", path.display(), e))?;
+    let source = path.display().to_string();
+    let file: FileConfig = toml::from_str(&data).map_err(|e| format!("{}: {}", source, e))?;
+
+    let mut values = HashMap::new();
+    for (key, raw) in file.values() {
+        let setting = SETTINGS
+            .iter()
+            .find(|setting| setting.key == key)
+            .expect("every config file key is a setting");
+        values.insert(
+            setting.key,
+            Value {
+                raw,
+                source: source.clone(),
+            },
+        );
+    }
+    Ok(values)
+}
+
+/// The config file. Values are checked by [`Config::apply`] like those from
+/// flags and the environment; the types here only reject values of the
+/// wrong kind early, with their position in the file.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    nws_api_base: Option<String>,
+    user_agent: Option<String>,
+    bind_address: Option<String>,
+    transport: Option<String>,
+    max_sessions: Option<u64>,
+    session_idle_timeout_secs: Option<f64>,
+    data_dir: Option<String>,
+    auth: AuthFile,
+    rate_limit: RateLimitFile,
+    readiness: ReadinessFile,
+    otlp: OtlpFile,
+    log: LogFile,
+    cache: CacheFile,
+    upstream: UpstreamFile,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuthFile {
+    api_keys: Option<Vec<String>>,
+    jwks_file: Option<String>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    resource: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitFile {
+    requests_per_minute: Option<f64>,
+    burst: Option<u64>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReadinessFile {
+    check_upstream: Option<bool>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OtlpFile {
+    endpoint: Option<String>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LogFile {
+    format: Option<String>,
+    level: Option<String>,
+    redact_coordinates: Option<bool>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheFile {
+    max_mb: Option<u64>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UpstreamFile {
+    timeout_secs: Option<f64>,
+    requests_per_second: Option<f64>,
+    max_queue_secs: Option<f64>,
+    max_attempts: Option<u64>,
+    retry_deadline_secs: Option<f64>,
+}
+
+impl FileConfig {
+    /// The settings given, by [`Setting::key`], as raw values; arrays are
+    /// joined with commas.
+    fn values(self) -> Vec<(&'static str, String)> {
+        fn text(value: Option<impl ToString>) -> Option<String> {
+            value.map(|value| value.to_string())
+        }
+
+        [
+            ("nws_api_base", self.nws_api_base),
+            ("user_agent", self.user_agent),
+            ("bind_address", self.bind_address),
+            ("transport", self.transport),
+            ("max_sessions", text(self.max_sessions)),
+            (
+                "session_idle_timeout_secs",
+                text(self.session_idle_timeout_secs),
+            ),
+            ("data_dir", self.data_dir),
+            (
+                "auth.api_keys",
+                self.auth.api_keys.map(|keys| keys.join(",")),
+            ),
+            ("auth.jwks_file", self.auth.jwks_file),
+            ("auth.issuer", self.auth.issuer),
+            ("auth.audience", self.auth.audience),
+            ("auth.resource", self.auth.resource),
+            (
+                "rate_limit.requests_per_minute",
+                text(self.rate_limit.requests_per_minute),
+            ),
+            ("rate_limit.burst", text(self.rate_limit.burst)),
+            (
+                "readiness.check_upstream",
+                text(self.readiness.check_upstream),
+            ),
+            ("otlp.endpoint", self.otlp.endpoint),
+            ("log.format", self.log.format),
+            ("log.level", self.log.level),
+            ("log.redact_coordinates", text(self.log.redact_coordinates)),
+            ("cache.max_mb", text(self.cache.max_mb)),
+            ("upstream.timeout_secs", text(self.upstream.timeout_secs)),
+            (
+                "upstream.requests_per_second",
+                text(self.upstream.requests_per_second),
+            ),
+            (
+                "upstream.max_queue_secs",
+                text(self.upstream.max_queue_secs),
+            ),
+            ("upstream.max_attempts", text(self.upstream.max_attempts)),
+            (
+                "upstream.retry_deadline_secs",
+                text(self.upstream.retry_deadline_secs),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(key, raw)| Some((key, raw?)))
+        .collect()
+    }
+}
+
+/// `$XDG_DATA_HOME/mcp-weather-rust`, falling back to
+/// `~/.local/share/mcp-weather-rust`.
+fn default_data_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join(env!("CARGO_PKG_NAME"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a config file unique to the calling test.
+    fn config_file(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("mcp-weather-{}-{}.toml", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(args: &[&str], env: &[(&str, &str)]) -> Result<Config, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Config::from_sources(&args, &env)
+    }
+
+    #[test]
+    fn defaults_apply_without_sources() {
+        let config = load(&[], &[]).unwrap();
+        assert_eq!(config.user_agent, "weather-app/2.0");
+        assert_eq!(config.max_sessions, 100);
+        assert_eq!(config.transport, Transport::Http);
+    }
+
+    #[test]
+    fn flags_override_env_which_overrides_the_file() {
+        let path = config_file(
+            "precedence",
+            "user_agent = \"file\"\nmax_sessions = 5\n\n[cache]\nmax_mb = 7\n",
+        );
+        let path = path.to_str().unwrap();
+
+        let config = load(&[], &[("MCP_WEATHER_CONFIG", path)]).unwrap();
+        assert_eq!(config.user_agent, "file");
+        assert_eq!(config.max_sessions, 5);
+        assert_eq!(config.cache_max_bytes, 7 * 1024 * 1024);
+
+        let env = [
+            ("MCP_WEATHER_CONFIG", path),
+            ("MCP_WEATHER_USER_AGENT", "env"),
+            ("MCP_WEATHER_MAX_SESSIONS", "6"),
+        ];
+        let config = load(&[], &env).unwrap();
+        assert_eq!(config.user_agent, "env");
+        assert_eq!(config.max_sessions, 6);
+        assert_eq!(config.cache_max_bytes, 7 * 1024 * 1024);
+
+        let config = load(&["--user-agent", "flag", "--max-sessions=8"], &env).unwrap();
+        assert_eq!(config.user_agent, "flag");
+        assert_eq!(config.max_sessions, 8);
+        assert_eq!(config.cache_max_bytes, 7 * 1024 * 1024);
+    }
+
+    #[test]
+    fn config_flag_takes_precedence_over_config_env() {
+        let flag_file = config_file("flag-file", "user_agent = \"flag file\"\n");
+        let env_file = config_file("env-file", "user_agent = \"env file\"\n");
+        let config = load(
+            &["--config", flag_file.to_str().unwrap()],
+            &[("MCP_WEATHER_CONFIG", env_file.to_str().unwrap())],
+        )
+        .unwrap();
+        assert_eq!(config.user_agent, "flag file");
+    }
+
+    #[test]
+    fn rejects_unknown_flags_and_file_keys() {
+        let error = load(&["--no-such-flag", "1"], &[]).unwrap_err();
+        assert!(
+            error.starts_with("Unknown argument '--no-such-flag'"),
+            "{error}"
+        );
+
+        let path = config_file("unknown-key", "[cache]\nmax_gb = 1\n");
+        let error = load(&["--config", path.to_str().unwrap()], &[]).unwrap_err();
+        assert!(error.contains("unknown field `max_gb`"), "{error}");
+
+        let error = load(&["--user-agent"], &[]).unwrap_err();
+        assert_eq!(error, "Missing value for --user-agent");
+    }
+
+    #[test]
+    fn reports_invalid_values_with_their_source() {
+        let error = load(&[], &[("MCP_WEATHER_MAX_SESSIONS", "0")]).unwrap_err();
+        assert_eq!(
+            error,
+            "Invalid value '0' for max_sessions (from MCP_WEATHER_MAX_SESSIONS): must be at least 1"
+        );
+
+        let error = load(&["--cache-max-mb", &u64::MAX.to_string()], &[]).unwrap_err();
+        assert!(error.ends_with("too large for this platform"), "{error}");
+
+        let path = config_file("wrong-type", "max_sessions = \"many\"\n");
+        let error = load(&["--config", path.to_str().unwrap()], &[]).unwrap_err();
+        assert!(error.contains("max_sessions"), "{error}");
+
+        let error = load(&["--nws-api-base", "ftp://example.com"], &[]).unwrap_err();
+        assert!(error.ends_with("must be an http or https URL"), "{error}");
+    }
+}
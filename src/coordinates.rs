@@ -0,0 +1,225 @@
+//! Validated latitude/longitude pairs for NWS API requests.
+
+use std::fmt;
+
+/// Approximate bounding boxes (south, north, west, east) of the states,
+/// territories and marine areas NWS issues forecasts and alerts for, by their
+/// alert `area` code. Areas that cross the antimeridian have a box on each
+/// side.
+#[rustfmt::skip]
+pub const AREA_BOUNDS: &[(&str, f64, f64, f64, f64)] = &[
+    ("AL", 30.1, 35.0, -88.5, -84.9),
+    ("AK", 51.2, 71.4, -180.0, -130.0),
+    ("AK", 51.2, 53.0, 172.4, 180.0),
+    ("AZ", 31.3, 37.0, -114.8, -109.0),
+    ("AR", 33.0, 36.5, -94.6, -89.6),
+    ("CA", 32.5, 42.0, -124.4, -114.1),
+    ("CO", 37.0, 41.0, -109.1, -102.0),
+    ("CT", 41.0, 42.1, -73.7, -71.8),
+    ("DE", 38.5, 39.8, -75.8, -75.0),
+    ("DC", 38.8, 39.0, -77.1, -76.9),
+    ("FL", 24.4, 31.0, -87.6, -80.0),
+    ("GA", 30.4, 35.0, -85.6, -80.8),
+    ("HI", 18.9, 22.2, -160.3, -154.8),
+    ("ID", 42.0, 49.0, -117.2, -111.0),
+    ("IL", 37.0, 42.5, -91.5, -87.5),
+    ("IN", 37.8, 41.8, -88.1, -84.8),
+    ("IA", 40.4, 43.5, -96.6, -90.1),
+    ("KS", 37.0, 40.0, -102.1, -94.6),
+    ("KY", 36.5, 39.1, -89.6, -82.0),
+    ("LA", 28.9, 33.0, -94.0, -88.8),
+    ("ME", 43.1, 47.5, -71.1, -67.0),
+    ("MD", 37.9, 39.7, -79.5, -75.0),
+    ("MA", 41.2, 42.9, -73.5, -69.9),
+    ("MI", 41.7, 48.3, -90.4, -82.4),
+    ("MN", 43.5, 49.4, -97.2, -89.5),
+    ("MS", 30.2, 35.0, -91.7, -88.1),
+    ("MO", 36.0, 40.6, -95.8, -89.1),
+    ("MT", 44.4, 49.0, -116.1, -104.0),
+    ("NE", 40.0, 43.0, -104.1, -95.3),
+    ("NV", 35.0, 42.0, -120.0, -114.0),
+    ("NH", 42.7, 45.3, -72.6, -70.6),
+    ("NJ", 38.9, 41.4, -75.6, -73.9),
+    ("NM", 31.3, 37.0, -109.1, -103.0),
+    ("NY", 40.5, 45.0, -79.8, -71.9),
+    ("NC", 33.8, 36.6, -84.3, -75.5),
+    ("ND", 45.9, 49.0, -104.1, -96.6),
+    ("OH", 38.4, 42.0, -84.8, -80.5),
+    ("OK", 33.6, 37.0, -103.0, -94.4),
+    ("OR", 42.0, 46.3, -124.6, -116.5),
+    ("PA", 39.7, 42.3, -80.5, -74.7),
+    ("RI", 41.1, 42.0, -71.9, -71.1),
+    ("SC", 32.0, 35.2, -83.4, -78.5),
+    ("SD", 42.5, 45.9, -104.1, -96.4),
+    ("TN", 35.0, 36.7, -90.3, -81.6),
+    ("TX", 25.8, 36.5, -106.6, -93.5),
+    ("UT", 37.0, 42.0, -114.1, -109.0),
+    ("VT", 42.7, 45.0, -73.4, -71.5),
+    ("VA", 36.5, 39.5, -83.7, -75.2),
+    ("WA", 45.5, 49.1, -124.8, -116.9),
+    ("WV", 37.2, 40.6, -82.6, -77.7),
+    ("WI", 42.5, 47.3, -92.9, -86.2),
+    ("WY", 41.0, 45.0, -111.1, -104.1),
+    ("AS", -14.6, -11.0, -171.1, -168.1),
+    ("GU", 13.2, 13.7, 144.6, 145.0),
+    ("MP", 14.1, 20.6, 144.9, 146.1),
+    ("PR", 17.9, 18.5, -68.0, -65.2),
+    ("VI", 17.7, 18.4, -65.1, -64.6),
+    ("AM", 17.0, 36.6, -83.0, -60.0),
+    ("AN", 35.0, 45.5, -77.5, -65.0),
+    ("GM", 18.0, 31.0, -98.0, -80.5),
+    ("LC", 42.3, 42.7, -83.0, -82.4),
+    ("LE", 41.3, 43.0, -83.5, -78.8),
+    ("LH", 43.0, 46.4, -84.8, -79.6),
+    ("LM", 41.6, 46.2, -88.1, -84.7),
+    ("LO", 43.1, 44.3, -79.9, -75.9),
+    ("LS", 46.3, 49.1, -92.3, -84.3),
+    ("PH", 15.0, 26.0, -164.0, -152.0),
+    ("PK", 51.0, 72.0, -180.0, -129.5),
+    ("PK", 51.0, 60.0, 170.0, 180.0),
+    ("PM", 11.0, 21.0, 143.0, 147.0),
+    ("PS", -15.5, -10.5, -172.0, -167.5),
+    ("PZ", 30.0, 49.0, -130.0, -117.0),
+    ("SL", 44.2, 45.1, -76.4, -74.6),
+];
+
+/// Precision accepted by the points endpoint; anything finer is answered with
+/// a 301 redirect to the rounded coordinates.
+const DECIMAL_PLACES: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Range-checks and rounds the coordinates, rejecting points outside NWS
+    /// coverage.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, String> {
+        if !latitude.is_finite() || !(-90.0..=90.0).contains(&latitude) {
+            return Err(format!(
+                "Invalid latitude {}: must be between -90 and 90.",
+                latitude
+            ));
+        }
+        if !longitude.is_finite() || !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!(
+                "Invalid longitude {}: must be between -180 and 180.",
+                longitude
+            ));
+        }
+
+        let coordinates = Self {
+            latitude: round(latitude),
+            longitude: round(longitude),
+        };
+        if coordinates.coverage_area().is_none() {
+            return Err(format!(
+                "The point {} is outside National Weather Service coverage. Forecasts are only available for the United States, its territories and coastal waters.",
+                coordinates
+            ));
+        }
+        Ok(coordinates)
+    }
+
+    /// Code of the first state, territory or marine area whose bounding box
+    /// contains this point.
+    pub fn coverage_area(&self) -> Option<&'static str> {
+        AREA_BOUNDS
+            .iter()
+            .find(|(_, south, north, west, east)| {
+                (*south..=*north).contains(&self.latitude)
+                    && (*west..=*east).contains(&self.longitude)
+            })
+            .map(|(code, ..)| *code)
+    }
+
+    /// Great-circle distance to another point in kilometers.
+    pub fn distance_km(&self, other: &Coordinates) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// Formats as `lat,lon`, the form used in NWS URLs.
+impl fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.latitude, self.longitude)
+    }
+}
+
+fn round(value: f64) -> f64 {
+    let scale = 10f64.powi(DECIMAL_PLACES);
+    let rounded = (value * scale).round() / scale;
+    // Avoid printing "-0"
+    if rounded == 0.0 { 0.0 } else { rounded }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        for (latitude, longitude) in [
+            (90.1, -100.0),
+            (-90.1, -100.0),
+            (40.0, 180.1),
+            (40.0, -180.1),
+            (f64::NAN, -100.0),
+            (40.0, f64::INFINITY),
+        ] {
+            let error = Coordinates::new(latitude, longitude).unwrap_err();
+            assert!(error.starts_with("Invalid"), "{error}");
+        }
+    }
+
+    #[test]
+    fn accepts_points_in_every_coverage_area() {
+        for (latitude, longitude, area) in [
+            (47.6062, -122.3321, "WA"),
+            (61.2181, -149.9003, "AK"),
+            (51.88, -176.6581, "AK"),
+            (52.9, 173.2, "AK"),
+            (21.3069, -157.8583, "HI"),
+            (18.4655, -66.1057, "PR"),
+            (18.3419, -64.9307, "VI"),
+            (13.4443, 144.7937, "GU"),
+            (15.1779, 145.7508, "MP"),
+            (-14.2756, -170.702, "AS"),
+            (27.0, -90.0, "GM"),
+        ] {
+            let coordinates = Coordinates::new(latitude, longitude).unwrap();
+            assert_eq!(coordinates.coverage_area(), Some(area), "{coordinates}");
+        }
+    }
+
+    #[test]
+    fn rejects_points_outside_coverage() {
+        for (latitude, longitude) in [(51.5074, -0.1278), (19.4326, -99.1332), (35.6762, 139.6503)]
+        {
+            let error = Coordinates::new(latitude, longitude).unwrap_err();
+            assert!(
+                error.contains("outside National Weather Service coverage"),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn rounds_to_four_decimal_places() {
+        let coordinates = Coordinates::new(47.606_249, -122.332_051).unwrap();
+        assert_eq!(coordinates.to_string(), "47.6062,-122.3321");
+        assert_eq!(
+            Coordinates::new(40.0, -105.0).unwrap().to_string(),
+            "40,-105"
+        );
+        assert_eq!(round(-0.000_04).to_string(), "0");
+        assert_eq!(round(0.000_05), 0.0001);
+    }
+}
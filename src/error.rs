@@ -0,0 +1,149 @@
+//! Errors raised while serving a tool call.
+
+use std::{fmt, time::Duration};
+
+use rmcp::model::{Content, IntoContents};
+
+/// Failure talking to the NWS API or validating tool arguments.
+///
+/// Tools return `Result<_, NwsError>`; rmcp reports the `Err` side to the
+/// client as a tool result with `isError: true` and the message below.
+#[derive(Debug)]
+pub enum NwsError {
+    /// The request could not be sent or the connection failed.
+    Network(String),
+    /// NWS did not answer within the client timeout.
+    Timeout,
+    /// Non-success status without a problem document.
+    Status(reqwest::StatusCode),
+    /// Non-success status with an NWS problem+json body.
+    Problem {
+        status: reqwest::StatusCode,
+        problem: ProblemDetails,
+    },
+    /// The circuit breaker is open after repeated upstream failures.
+    Unavailable { retry_in: Duration },
+    /// The server's own budget for NWS requests is used up.
+    RateLimited { retry_in: Duration },
+    /// The response body did not match the expected schema.
+    Decode(String),
+    /// The tool arguments were rejected before any request was made.
+    InvalidInput(String),
+}
+
+/// RFC 7807 problem document returned by NWS for failed requests.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProblemDetails {
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    pub instance: Option<String>,
+    /// Identifier to quote when reporting the failure to NWS.
+    #[serde(rename = "correlationId")]
+    pub correlation_id: Option<String>,
+}
+
+impl fmt::Display for ProblemDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.title, &self.detail) {
+            (Some(title), Some(detail)) => write!(f, "{}: {}", title, detail)?,
+            (Some(text), None) | (None, Some(text)) => write!(f, "{}", text)?,
+            (None, None) => write!(f, "no details provided")?,
+        }
+        if let Some(correlation_id) = &self.correlation_id {
+            write!(f, " (correlation ID {})", correlation_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl NwsError {
+    /// Whether the same request may succeed if tried again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NwsError::Network(_) | NwsError::Timeout => true,
+            NwsError::Status(status) | NwsError::Problem { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            NwsError::Unavailable { .. }
+            | NwsError::RateLimited { .. }
+            | NwsError::Decode(_)
+            | NwsError::InvalidInput(_) => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NwsError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            NwsError::Timeout
+        } else if e.is_decode() {
+            NwsError::Decode(e.to_string())
+        } else {
+            NwsError::Network(e.to_string())
+        }
+    }
+}
+
+impl fmt::Display for NwsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NwsError::Network(e) => write!(
+                f,
+                "Could not reach the National Weather Service API ({}). This is usually temporary; try again shortly.",
+                e
+            ),
+            NwsError::Timeout => write!(
+                f,
+                "The National Weather Service API did not respond in time. Try again shortly."
+            ),
+            NwsError::Status(status) => {
+                write!(f, "The National Weather Service API returned {}.", status)?;
+                write_hint(f, *status)
+            }
+            NwsError::Problem { status, problem } => {
+                write!(
+                    f,
+                    "The National Weather Service API returned {}: {}",
+                    status, problem
+                )?;
+                write_hint(f, *status)
+            }
+            NwsError::Unavailable { retry_in } => write!(
+                f,
+                "The National Weather Service API is unavailable after repeated failures, so requests are paused. Try again in {} seconds.",
+                retry_in.as_secs().max(1)
+            ),
+            NwsError::RateLimited { retry_in } => write!(
+                f,
+                "Too many National Weather Service requests are in progress from this server. Try again in {} seconds.",
+                retry_in.as_secs_f64().ceil().max(1.0)
+            ),
+            NwsError::Decode(e) => write!(
+                f,
+                "Received an unexpected response from the National Weather Service API: {}",
+                e
+            ),
+            NwsError::InvalidInput(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for NwsError {}
+
+impl IntoContents for NwsError {
+    fn into_contents(self) -> Vec<Content> {
+        vec![Content::text(self.to_string())]
+    }
+}
+
+/// Appends advice on whether and how to retry after a status code.
+fn write_hint(f: &mut fmt::Formatter<'_>, status: reqwest::StatusCode) -> fmt::Result {
+    let hint = match status.as_u16() {
+        400 => "Check the tool arguments.",
+        404 => "No data exists for the requested location or resource.",
+        429 => "Too many requests; wait before retrying.",
+        500..=599 => "The service is having problems; try again later.",
+        _ => return Ok(()),
+    };
+    write!(f, " {}", hint)
+}
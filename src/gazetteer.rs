@@ -0,0 +1,359 @@
+//! Offline lookup of US place names and ZIP codes to coordinates.
+//!
+//! The bundled dataset lives in `data/gazetteer.csv` and is compiled into the
+//! binary. Each row is `name,state,zip,latitude,longitude`; rows without a ZIP
+//! code describe a place, rows with one describe a ZIP centroid.
+//! `scripts/build_gazetteer.py` regenerates it from the zipcodes ZIP code
+//! database and GeoNames places.
+
+use std::{collections::HashMap, fmt, sync::OnceLock};
+
+const BUNDLED_DATA: &str = include_str!("../data/gazetteer.csv");
+
+/// Maximum number of candidates listed when a name is ambiguous.
+const MAX_CANDIDATES: usize = 10;
+
+/// Two-letter codes and names of the states and territories.
+pub const STATES: &[(&str, &str)] = &[
+    ("AL", "Alabama"),
+    ("AK", "Alaska"),
+    ("AZ", "Arizona"),
+    ("AR", "Arkansas"),
+    ("CA", "California"),
+    ("CO", "Colorado"),
+    ("CT", "Connecticut"),
+    ("DE", "Delaware"),
+    ("DC", "District of Columbia"),
+    ("FL", "Florida"),
+    ("GA", "Georgia"),
+    ("HI", "Hawaii"),
+    ("ID", "Idaho"),
+    ("IL", "Illinois"),
+    ("IN", "Indiana"),
+    ("IA", "Iowa"),
+    ("KS", "Kansas"),
+    ("KY", "Kentucky"),
+    ("LA", "Louisiana"),
+    ("ME", "Maine"),
+    ("MD", "Maryland"),
+    ("MA", "Massachusetts"),
+    ("MI", "Michigan"),
+    ("MN", "Minnesota"),
+    ("MS", "Mississippi"),
+    ("MO", "Missouri"),
+    ("MT", "Montana"),
+    ("NE", "Nebraska"),
+    ("NV", "Nevada"),
+    ("NH", "New Hampshire"),
+    ("NJ", "New Jersey"),
+    ("NM", "New Mexico"),
+    ("NY", "New York"),
+    ("NC", "North Carolina"),
+    ("ND", "North Dakota"),
+    ("OH", "Ohio"),
+    ("OK", "Oklahoma"),
+    ("OR", "Oregon"),
+    ("PA", "Pennsylvania"),
+    ("RI", "Rhode Island"),
+    ("SC", "South Carolina"),
+    ("SD", "South Dakota"),
+    ("TN", "Tennessee"),
+    ("TX", "Texas"),
+    ("UT", "Utah"),
+    ("VT", "Vermont"),
+    ("VA", "Virginia"),
+    ("WA", "Washington"),
+    ("WV", "West Virginia"),
+    ("WI", "Wisconsin"),
+    ("WY", "Wyoming"),
+    ("AS", "American Samoa"),
+    ("GU", "Guam"),
+    ("MP", "Northern Mariana Islands"),
+    ("PR", "Puerto Rico"),
+    ("VI", "Virgin Islands"),
+];
+
+/// Normalizes a state or territory name or postal code to its two-letter code.
+pub fn state_code(input: &str) -> Option<&'static str> {
+    let input = normalize(input);
+    STATES
+        .iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(&input) || normalize(name) == input)
+        .map(|(code, _)| *code)
+}
+
+#[derive(Debug, Clone)]
+pub struct Place {
+    pub name: String,
+    pub state: String,
+    pub zip: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.zip {
+            Some(zip) => write!(f, "{}, {} {}", self.name, self.state, zip)?,
+            None => write!(f, "{}, {}", self.name, self.state)?,
+        }
+        write!(f, " ({:.4}, {:.4})", self.latitude, self.longitude)
+    }
+}
+
+#[derive(Debug)]
+pub enum LookupError {
+    NotFound(String),
+    Ambiguous(String, Vec<Place>),
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LookupError::NotFound(query) => write!(
+                f,
+                "Location '{}' was not found. Use a \"City, ST\" name, a 5-digit ZIP code, or latitude and longitude.",
+                query
+            ),
+            LookupError::Ambiguous(query, candidates) => {
+                writeln!(
+                    f,
+                    "Location '{}' is ambiguous. Specify one of the following:",
+                    query
+                )?;
+                for place in candidates {
+                    writeln!(f, "- {}", place)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gazetteer {
+    places: Vec<Place>,
+    by_zip: HashMap<String, usize>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl Gazetteer {
+    /// The dataset compiled into the binary.
+    pub fn bundled() -> &'static Gazetteer {
+        static BUNDLED: OnceLock<Gazetteer> = OnceLock::new();
+        BUNDLED.get_or_init(|| {
+            Gazetteer::from_csv(BUNDLED_DATA).expect("bundled gazetteer data is invalid")
+        })
+    }
+
+    pub fn from_csv(data: &str) -> Result<Self, String> {
+        let mut gazetteer = Gazetteer::default();
+
+        let rows = data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .skip(1); // header
+
+        for (index, line) in rows {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [name, state, zip, latitude, longitude] = fields[..] else {
+                return Err(format!("Invalid gazetteer row on line {}", index + 1));
+            };
+            let parse = |value: &str| {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid coordinate '{}' on line {}", value, index + 1))
+            };
+            gazetteer.insert(Place {
+                name: name.to_string(),
+                state: state.to_uppercase(),
+                zip: (!zip.is_empty()).then(|| zip.to_string()),
+                latitude: parse(latitude)?,
+                longitude: parse(longitude)?,
+            });
+        }
+
+        Ok(gazetteer)
+    }
+
+    fn insert(&mut self, place: Place) {
+        let index = self.places.len();
+        match &place.zip {
+            Some(zip) => {
+                self.by_zip.insert(zip.clone(), index);
+            }
+            None => self
+                .by_name
+                .entry(normalize(&place.name))
+                .or_default()
+                .push(index),
+        }
+        self.places.push(place);
+    }
+
+    /// Resolves a free-form location such as `"Seattle, WA"`, `"Portland"` or
+    /// `"98101"` to a single place.
+    pub fn lookup(&self, query: &str) -> Result<&Place, LookupError> {
+        let not_found = || LookupError::NotFound(query.trim().to_string());
+
+        // ZIP or ZIP+4
+        let zip = query.trim().split('-').next().unwrap_or_default();
+        if zip.len() == 5 && zip.bytes().all(|b| b.is_ascii_digit()) {
+            return self
+                .by_zip
+                .get(zip)
+                .map(|&index| &self.places[index])
+                .ok_or_else(not_found);
+        }
+
+        let (name, state) = match query.rsplit_once(',') {
+            Some((name, state)) => (name, Some(state_code(state).ok_or_else(not_found)?)),
+            None => (query, None),
+        };
+
+        let candidates: Vec<&Place> = self
+            .by_name
+            .get(&normalize(name))
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.places[index])
+            .filter(|place| state.is_none_or(|state| place.state == state))
+            .collect();
+
+        match candidates.as_slice() {
+            [] => Err(not_found()),
+            [place] => Ok(place),
+            _ => Err(LookupError::Ambiguous(
+                query.trim().to_string(),
+                candidates
+                    .into_iter()
+                    .take(MAX_CANDIDATES)
+                    .cloned()
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// Lowercases, drops periods and collapses whitespace, expanding the common
+/// "St." abbreviation so "St. Louis" and "Saint Louis" match.
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .replace('.', " ")
+        .split_whitespace()
+        .map(|word| if word == "st" { "saint" } else { word })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinates::Coordinates;
+
+    const SAMPLE: &str = "\
+# comment
+name,state,zip,latitude,longitude
+Seattle,WA,,47.6062,-122.3321
+St. Louis,MO,,38.6273,-90.1979
+Saint Louis,MI,,43.4084,-84.6067
+Portland,OR,,45.5234,-122.6762
+Portland,ME,,43.6615,-70.2553
+Seattle,WA,98101,47.6114,-122.3305
+";
+
+    fn sample() -> Gazetteer {
+        Gazetteer::from_csv(SAMPLE).unwrap()
+    }
+
+    #[test]
+    fn resolves_zip_and_zip_plus_four() {
+        let gazetteer = sample();
+        for query in ["98101", " 98101-1234 "] {
+            let place = gazetteer.lookup(query).unwrap();
+            assert_eq!(place.zip.as_deref(), Some("98101"));
+            assert_eq!(place.name, "Seattle");
+        }
+        assert!(matches!(
+            gazetteer.lookup("98199"),
+            Err(LookupError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn matches_saint_abbreviation_and_state() {
+        let gazetteer = sample();
+        for query in ["St. Louis, MO", "saint louis, missouri", "St Louis,mo"] {
+            assert_eq!(gazetteer.lookup(query).unwrap().state, "MO");
+        }
+        assert_eq!(gazetteer.lookup("Saint Louis, MI").unwrap().state, "MI");
+        assert!(matches!(
+            gazetteer.lookup("St. Louis"),
+            Err(LookupError::Ambiguous(_, candidates)) if candidates.len() == 2
+        ));
+    }
+
+    #[test]
+    fn lists_candidates_for_ambiguous_names() {
+        let gazetteer = sample();
+        let Err(LookupError::Ambiguous(query, candidates)) = gazetteer.lookup("Portland") else {
+            panic!("Portland should be ambiguous");
+        };
+        assert_eq!(query, "Portland");
+        let states: Vec<&str> = candidates
+            .iter()
+            .map(|place| place.state.as_str())
+            .collect();
+        assert_eq!(states, ["OR", "ME"]);
+        assert_eq!(gazetteer.lookup("Portland, Oregon").unwrap().state, "OR");
+    }
+
+    #[test]
+    fn rejects_unknown_states_and_places() {
+        let gazetteer = sample();
+        assert!(matches!(
+            gazetteer.lookup("Seattle, XX"),
+            Err(LookupError::NotFound(_))
+        ));
+        assert!(matches!(
+            gazetteer.lookup("Atlantis"),
+            Err(LookupError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn reports_malformed_rows() {
+        let error =
+            Gazetteer::from_csv("name,state,zip,latitude,longitude\nSeattle,WA,,north,-122\n")
+                .unwrap_err();
+        assert!(error.contains("line 2"), "{error}");
+        assert!(Gazetteer::from_csv("header\nSeattle,WA\n").is_err());
+    }
+
+    #[test]
+    fn bundled_data_covers_every_state() {
+        let gazetteer = Gazetteer::bundled();
+        for (code, _) in STATES {
+            assert!(
+                gazetteer.places.iter().any(|place| place.state == *code),
+                "no places in {code}"
+            );
+        }
+        assert_eq!(gazetteer.lookup("Seattle, WA").unwrap().zip, None);
+        assert_eq!(gazetteer.lookup("63101").unwrap().state, "MO");
+        assert!(gazetteer.lookup("St. Louis, MO").is_ok());
+        assert!(matches!(
+            gazetteer.lookup("Portland"),
+            Err(LookupError::Ambiguous(_, candidates)) if candidates.len() == MAX_CANDIDATES
+        ));
+    }
+
+    #[test]
+    fn bundled_places_lie_within_nws_coverage() {
+        for place in &Gazetteer::bundled().places {
+            let coordinates = Coordinates::new(place.latitude, place.longitude);
+            assert!(coordinates.is_ok(), "{place}");
+        }
+    }
+}
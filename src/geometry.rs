@@ -0,0 +1,386 @@
+//! Alert areas as polygons, and where a point lies relative to them.
+//!
+//! Alert polygons and zone boundaries arrive as GeoJSON. An alert issued for
+//! several zones covers the union of their boundaries; borders shared between
+//! those zones are dropped so that distances are measured to the outer edge
+//! of the alert rather than to an internal zone line. A shared border is
+//! recognised when each vertex of one side lies on the other side, even if
+//! one zone stores it with more vertices. Borders the two zones trace through
+//! different points still count as outer edges.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::coordinates::Coordinates;
+
+/// Kilometers per degree of latitude.
+const KM_PER_DEGREE: f64 = 111.2;
+/// Size, in degrees, of the grid cells used to find vertices near an edge.
+const CELL_DEGREES: f64 = 0.05;
+/// How far, in degrees (about 1 m), a vertex may lie from an edge and still
+/// count as on it.
+const ON_EDGE_DEGREES: f64 = 1e-5;
+
+/// A `(longitude, latitude)` position, in GeoJSON order.
+type Position = (f64, f64);
+/// Outer ring followed by any holes; each ring is closed.
+type Polygon = Vec<Vec<Position>>;
+
+/// The polygons of a GeoJSON `Polygon`, `MultiPolygon` or
+/// `GeometryCollection`. Other geometry types deserialize as empty.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "RawGeometry")]
+pub struct Geometry {
+    polygons: Vec<Polygon>,
+}
+
+#[derive(Deserialize)]
+struct RawGeometry {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    coordinates: serde_json::Value,
+    #[serde(default)]
+    geometries: Vec<RawGeometry>,
+}
+
+impl From<RawGeometry> for Geometry {
+    fn from(raw: RawGeometry) -> Self {
+        let polygons = match raw.kind.as_str() {
+            "Polygon" => serde_json::from_value::<Vec<Vec<Vec<f64>>>>(raw.coordinates)
+                .map(|rings| vec![polygon(rings)])
+                .unwrap_or_default(),
+            "MultiPolygon" => serde_json::from_value::<Vec<Vec<Vec<Vec<f64>>>>>(raw.coordinates)
+                .map(|polygons| polygons.into_iter().map(polygon).collect())
+                .unwrap_or_default(),
+            "GeometryCollection" => raw
+                .geometries
+                .into_iter()
+                .flat_map(|geometry| Geometry::from(geometry).polygons)
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self { polygons }
+    }
+}
+
+/// Keeps the longitude and latitude of each position, dropping altitude and
+/// malformed positions.
+fn polygon(rings: Vec<Vec<Vec<f64>>>) -> Polygon {
+    rings
+        .into_iter()
+        .map(|ring| {
+            ring.into_iter()
+                .filter_map(|position| match position.as_slice() {
+                    [longitude, latitude, ..] => Some((*longitude, *latitude)),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl Geometry {
+    pub fn is_empty(&self) -> bool {
+        self.polygons.is_empty()
+    }
+
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        let point = (point.longitude, point.latitude);
+        // Even-odd crossings over all rings also accounts for holes
+        self.polygons.iter().any(|polygon| {
+            polygon
+                .iter()
+                .flat_map(|ring| ring.windows(2))
+                .filter(|edge| crosses(point, edge[0], edge[1]))
+                .count()
+                % 2
+                == 1
+        })
+    }
+}
+
+/// Whether a ray from `point` towards increasing longitude crosses the edge.
+fn crosses(point: Position, a: Position, b: Position) -> bool {
+    let (x, y) = point;
+    if (a.1 > y) == (b.1 > y) {
+        return false;
+    }
+    let crossing = a.0 + (y - a.1) / (b.1 - a.1) * (b.0 - a.0);
+    x < crossing
+}
+
+/// Where a point lies relative to an alert area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Proximity {
+    pub inside: bool,
+    /// Distance from the point to the nearest edge of the area.
+    pub edge_km: f64,
+}
+
+impl Proximity {
+    /// Whether the area covers the point or comes within `radius_km` of it.
+    pub fn within(&self, radius_km: f64) -> bool {
+        self.inside || self.edge_km <= radius_km
+    }
+}
+
+/// Locates `point` relative to the union of `geometries`. Returns `None` if
+/// they have no polygons.
+pub fn proximity(point: &Coordinates, geometries: &[&Geometry]) -> Option<Proximity> {
+    if geometries.iter().all(|geometry| geometry.is_empty()) {
+        return None;
+    }
+    let inside = geometries.iter().any(|geometry| geometry.contains(point));
+
+    let rings: Vec<&Vec<Position>> = geometries
+        .iter()
+        .flat_map(|geometry| &geometry.polygons)
+        .flatten()
+        .collect();
+    let mut grid: HashMap<Cell, Vec<Position>> = HashMap::new();
+    for &position in rings.iter().copied().flatten() {
+        grid.entry(cell(position)).or_default().push(position);
+    }
+
+    // Edges shared by two rings are internal borders of the union
+    let mut edges: HashMap<(Vertex, Vertex), (Position, Position, usize)> = HashMap::new();
+    for ring in rings {
+        for edge in ring.windows(2) {
+            for (start, end) in split(edge[0], edge[1], &grid) {
+                let (a, b) = (vertex(start), vertex(end));
+                let key = if a <= b { (a, b) } else { (b, a) };
+                edges.entry(key).or_insert((start, end, 0)).2 += 1;
+            }
+        }
+    }
+
+    let edge_km = edges
+        .values()
+        .filter(|(.., count)| *count == 1)
+        .map(|(a, b, _)| segment_distance_km(point, *a, *b))
+        .fold(f64::INFINITY, f64::min);
+    Some(Proximity {
+        inside,
+        edge_km: if edge_km.is_finite() { edge_km } else { 0.0 },
+    })
+}
+
+/// A position rounded to about 10 cm, so that a border stored by two zones
+/// compares equal.
+type Vertex = (i64, i64);
+
+fn vertex((longitude, latitude): Position) -> Vertex {
+    (
+        (longitude * 1e6).round() as i64,
+        (latitude * 1e6).round() as i64,
+    )
+}
+
+type Cell = (i64, i64);
+
+fn cell((longitude, latitude): Position) -> Cell {
+    (
+        (longitude / CELL_DEGREES).floor() as i64,
+        (latitude / CELL_DEGREES).floor() as i64,
+    )
+}
+
+/// Splits the edge `a`-`b` at every vertex in `grid` that lies on it, so that
+/// a border one zone stores with more vertices than its neighbour still
+/// matches piece for piece.
+fn split(
+    a: Position,
+    b: Position,
+    grid: &HashMap<Cell, Vec<Position>>,
+) -> Vec<(Position, Position)> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        return vec![(a, b)];
+    }
+
+    let low = cell((
+        a.0.min(b.0) - ON_EDGE_DEGREES,
+        a.1.min(b.1) - ON_EDGE_DEGREES,
+    ));
+    let high = cell((
+        a.0.max(b.0) + ON_EDGE_DEGREES,
+        a.1.max(b.1) + ON_EDGE_DEGREES,
+    ));
+    let cells = (high.0 - low.0 + 1).saturating_mul(high.1 - low.1 + 1);
+    // Long edges span more cells than there are occupied ones
+    let candidates: Box<dyn Iterator<Item = &Position>> = if cells > grid.len() as i64 {
+        Box::new(grid.values().flatten())
+    } else {
+        Box::new(
+            (low.0..=high.0)
+                .flat_map(|x| (low.1..=high.1).map(move |y| (x, y)))
+                .filter_map(|key| grid.get(&key))
+                .flatten(),
+        )
+    };
+
+    let (a_vertex, b_vertex) = (vertex(a), vertex(b));
+    let mut splits: Vec<(f64, Position)> = candidates
+        .filter_map(|&p| {
+            let (px, py) = (p.0 - a.0, p.1 - a.1);
+            let t = (px * dx + py * dy) / (length * length);
+            let offset = (px * dy - py * dx).abs() / length;
+            let on_edge = (0.0..=1.0).contains(&t)
+                && offset <= ON_EDGE_DEGREES
+                && vertex(p) != a_vertex
+                && vertex(p) != b_vertex;
+            on_edge.then_some((t, p))
+        })
+        .collect();
+    splits.sort_by(|x, y| x.0.total_cmp(&y.0));
+    splits.dedup_by_key(|(_, p)| vertex(*p));
+
+    let mut pieces = Vec::with_capacity(splits.len() + 1);
+    let mut start = a;
+    for (_, p) in splits {
+        pieces.push((start, p));
+        start = p;
+    }
+    pieces.push((start, b));
+    pieces
+}
+
+/// Distance from `point` to the segment `a`-`b`, on a flat projection around
+/// the point, which is accurate enough at the scale of an alert area.
+fn segment_distance_km(point: &Coordinates, a: Position, b: Position) -> f64 {
+    let scale_x = KM_PER_DEGREE * point.latitude.to_radians().cos();
+    let project = |(longitude, latitude): Position| {
+        (
+            (longitude - point.longitude) * scale_x,
+            (latitude - point.latitude) * KM_PER_DEGREE,
+        )
+    };
+    let (ax, ay) = project(a);
+    let (bx, by) = project(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (-(ax * dx + ay * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    (ax + t * dx).hypot(ay + t * dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn geometry(value: serde_json::Value) -> Geometry {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// A closed axis-aligned ring from its corners.
+    fn square(west: f64, south: f64, east: f64, north: f64) -> serde_json::Value {
+        json!([
+            [west, south],
+            [east, south],
+            [east, north],
+            [west, north],
+            [west, south]
+        ])
+    }
+
+    fn point(latitude: f64, longitude: f64) -> Coordinates {
+        Coordinates::new(latitude, longitude).unwrap()
+    }
+
+    #[test]
+    fn contains_respects_holes() {
+        let donut = geometry(json!({
+            "type": "Polygon",
+            "coordinates": [square(-100.0, 40.0, -99.0, 41.0), square(-99.6, 40.4, -99.4, 40.6)],
+        }));
+        assert!(donut.contains(&point(40.2, -99.5)));
+        assert!(!donut.contains(&point(40.5, -99.5)));
+        assert!(!donut.contains(&point(41.5, -99.5)));
+    }
+
+    #[test]
+    fn contains_any_part_of_a_multipolygon_or_collection() {
+        let multi = geometry(json!({
+            "type": "MultiPolygon",
+            "coordinates": [[square(-100.0, 40.0, -99.0, 41.0)], [square(-90.0, 30.0, -89.0, 31.0)]],
+        }));
+        assert!(multi.contains(&point(40.5, -99.5)));
+        assert!(multi.contains(&point(30.5, -89.5)));
+        assert!(!multi.contains(&point(35.0, -95.0)));
+
+        let collection = geometry(json!({
+            "type": "GeometryCollection",
+            "geometries": [
+                {"type": "Point", "coordinates": [-95.0, 35.0]},
+                {"type": "Polygon", "coordinates": [square(-90.0, 30.0, -89.0, 31.0)]},
+            ],
+        }));
+        assert!(collection.contains(&point(30.5, -89.5)));
+        assert!(!collection.contains(&point(35.0, -95.0)));
+    }
+
+    #[test]
+    fn unsupported_geometries_are_empty() {
+        let line =
+            geometry(json!({"type": "LineString", "coordinates": [[-95.0, 35.0], [-94.0, 35.0]]}));
+        assert!(line.is_empty());
+        assert_eq!(proximity(&point(35.0, -95.0), &[&line]), None);
+    }
+
+    #[test]
+    fn measures_distance_to_the_nearest_edge() {
+        let area = geometry(
+            json!({"type": "Polygon", "coordinates": [square(-100.0, 40.0, -99.0, 41.0)]}),
+        );
+        let outside = proximity(&point(39.5, -99.5), &[&area]).unwrap();
+        assert!(!outside.inside);
+        assert!((outside.edge_km - 55.6).abs() < 0.5, "{outside:?}");
+        assert!(outside.within(60.0));
+        assert!(!outside.within(50.0));
+
+        let inside = proximity(&point(40.5, -99.5), &[&area]).unwrap();
+        assert!(inside.inside);
+        assert!(inside.within(0.0));
+    }
+
+    #[test]
+    fn ignores_borders_shared_between_zones() {
+        let west = geometry(
+            json!({"type": "Polygon", "coordinates": [square(-100.0, 40.0, -99.0, 41.0)]}),
+        );
+        let east =
+            geometry(json!({"type": "Polygon", "coordinates": [square(-99.0, 40.0, -98.0, 41.0)]}));
+        // Next to the shared border, half a degree from the outer edges
+        let near_border = point(40.5, -99.01);
+        let union = proximity(&near_border, &[&west, &east]).unwrap();
+        assert!(union.inside);
+        assert!((union.edge_km - 55.6).abs() < 0.5, "{union:?}");
+    }
+
+    #[test]
+    fn ignores_shared_borders_stored_with_extra_vertices() {
+        let west = geometry(
+            json!({"type": "Polygon", "coordinates": [square(-100.0, 40.0, -99.0, 41.0)]}),
+        );
+        // The east zone traces the shared border through two extra vertices
+        let east = geometry(json!({
+            "type": "Polygon",
+            "coordinates": [[
+                [-99.0, 40.0], [-98.0, 40.0], [-98.0, 41.0], [-99.0, 41.0],
+                [-99.0, 40.7], [-99.0, 40.2], [-99.0, 40.0],
+            ]],
+        }));
+        let union = proximity(&point(40.5, -99.01), &[&west, &east]).unwrap();
+        assert!((union.edge_km - 55.6).abs() < 0.5, "{union:?}");
+        let union = proximity(&point(40.5, -98.99), &[&east, &west]).unwrap();
+        assert!((union.edge_km - 55.6).abs() < 0.5, "{union:?}");
+    }
+}
@@ -0,0 +1,114 @@
+//! Health, readiness and version endpoints for orchestrators and load
+//! balancers. They are served without authentication or rate limits.
+//!
+//! - `/healthz` answers as long as the process is serving requests.
+//! - `/readyz` fails while the NWS circuit breaker is open and, if enabled,
+//!   while a request to the NWS API root fails. That check is cached so
+//!   frequent probes don't add NWS traffic.
+//! - `/version` reports the crate version, git commit and transports.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+
+use crate::{Weather, circuit_breaker::CircuitStatus};
+
+/// How long an upstream check result is reused.
+const CHECK_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct Health {
+    weather: Weather,
+    check_upstream: bool,
+    transports: &'static [&'static str],
+    /// When the upstream was last checked, and the error if it failed.
+    last_check: Mutex<Option<(Instant, Result<(), String>)>>,
+}
+
+impl Health {
+    pub fn new(
+        weather: Weather,
+        check_upstream: bool,
+        transports: &'static [&'static str],
+    ) -> Self {
+        Self {
+            weather,
+            check_upstream,
+            transports,
+            last_check: Mutex::new(None),
+        }
+    }
+
+    async fn upstream(&self) -> Result<(), String> {
+        if let Some((checked_at, result)) = &*self.last_check.lock().unwrap()
+            && checked_at.elapsed() < CHECK_TTL
+        {
+            return result.clone();
+        }
+
+        let result = self
+            .weather
+            .ping_upstream()
+            .await
+            .map_err(|e| e.to_string());
+        if let Err(e) = &result {
+            tracing::warn!("Readiness check failed: {}", e);
+        }
+        *self.last_check.lock().unwrap() = Some((Instant::now(), result.clone()));
+        result
+    }
+}
+
+pub fn router(health: Arc<Health>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+        .with_state(health)
+}
+
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readyz(State(health): State<Arc<Health>>) -> (StatusCode, Json<serde_json::Value>) {
+    let circuit = health.weather.circuit_status();
+    let upstream = if health.check_upstream {
+        Some(health.upstream().await)
+    } else {
+        None
+    };
+
+    let ready = circuit != CircuitStatus::Open && !matches!(upstream, Some(Err(_)));
+    let mut body = serde_json::json!({
+        "status": if ready { "ready" } else { "unavailable" },
+        "circuit": circuit,
+    });
+    match upstream {
+        Some(Ok(())) => body["upstream"] = "ok".into(),
+        Some(Err(e)) => {
+            body["upstream"] = "unreachable".into();
+            body["error"] = e.into();
+        }
+        None => body["upstream"] = "not_checked".into(),
+    }
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(body))
+}
+
+async fn version(State(health): State<Arc<Health>>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("GIT_SHA"),
+        "transports": health.transports,
+    }))
+}
@@ -0,0 +1,343 @@
+//! Log output: human-readable text or one JSON object per line, with secrets
+//! and, optionally, precise coordinates redacted.
+//!
+//! JSON lines start with `timestamp`, `level` and `target`, followed by the
+//! fields of the enclosing spans (such as `session_id` and `tool`), the name
+//! of the innermost span as `span`, then `message` and the event's other
+//! fields (such as `status`, `url` and `latency_ms`). Span fields meant only for trace export
+//! (`otel.*`, `traceparent`) are left out.
+//!
+//! Redaction happens on the formatted line, so it also covers dependencies'
+//! log lines, such as rmcp's dumps of request headers.
+
+use std::{
+    fmt::{self, Write as _},
+    io,
+};
+
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields, MakeWriter, format::Writer},
+    registry::LookupSpan,
+};
+
+/// Formats span and event fields as the members of a JSON object, without
+/// the surrounding braces.
+#[derive(Debug, Default)]
+pub struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = JsonVisitor::default();
+        fields.record(&mut visitor);
+        writer.write_str(&visitor.members)
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &tracing::span::Record<'_>,
+    ) -> fmt::Result {
+        let mut visitor = JsonVisitor::default();
+        fields.record(&mut visitor);
+        if !current.fields.is_empty() && !visitor.members.is_empty() {
+            current.fields.push(',');
+        }
+        current.fields.push_str(&visitor.members);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct JsonVisitor {
+    members: String,
+}
+
+impl JsonVisitor {
+    fn push(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name().starts_with("otel.") || field.name() == "traceparent" {
+            return;
+        }
+        if !self.members.is_empty() {
+            self.members.push(',');
+        }
+        let _ = write!(
+            self.members,
+            "{}:{}",
+            serde_json::Value::from(field.name()),
+            value
+        );
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format!("{:?}", value).into());
+    }
+}
+
+/// Writes each event as one JSON object per line.
+#[derive(Debug, Default)]
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        write!(
+            writer,
+            "{{\"timestamp\":\"{}\",\"level\":\"{}\",\"target\":{}",
+            timestamp,
+            metadata.level(),
+            serde_json::Value::from(metadata.target())
+        )?;
+
+        if let Some(scope) = ctx.event_scope() {
+            let mut leaf = None;
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<FormattedFields<N>>()
+                    && !fields.is_empty()
+                {
+                    write!(writer, ",{}", fields.fields)?;
+                }
+                leaf = Some(span.name());
+            }
+            if let Some(name) = leaf {
+                write!(writer, ",\"span\":{}", serde_json::Value::from(name))?;
+            }
+        }
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        if !visitor.members.is_empty() {
+            write!(writer, ",{}", visitor.members)?;
+        }
+        writeln!(writer, "}}")
+    }
+}
+
+/// Wraps a writer factory so every line written is redacted first.
+#[derive(Debug, Clone, Copy)]
+pub struct Redacting<M> {
+    inner: M,
+    coordinates: bool,
+}
+
+impl<M> Redacting<M> {
+    /// Bearer tokens are always redacted; coordinates only if `coordinates`.
+    pub fn new(inner: M, coordinates: bool) -> Self {
+        Self { inner, coordinates }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            coordinates: self.coordinates,
+        }
+    }
+}
+
+pub struct RedactingWriter<W> {
+    inner: W,
+    coordinates: bool,
+}
+
+impl<W: io::Write> io::Write for RedactingWriter<W> {
+    // The fmt layer writes each formatted event with a single call
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        let mut redacted = redact_bearer_tokens(&text);
+        if self.coordinates {
+            redacted = coarsen_coordinates(&redacted);
+        }
+        self.inner.write_all(redacted.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Replaces the credential after every `Bearer ` with `[REDACTED]`.
+fn redact_bearer_tokens(text: &str) -> String {
+    const SCHEME: &str = "bearer ";
+    // ASCII lowercasing keeps byte offsets aligned with `text`
+    let lower = text.to_ascii_lowercase();
+    let mut result = String::with_capacity(text.len());
+    let mut rest = 0;
+    while let Some(found) = lower[rest..].find(SCHEME) {
+        let token_start = rest + found + SCHEME.len();
+        let token_end = text[token_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "-._~+/=".contains(c)))
+            .map_or(text.len(), |end| token_start + end);
+        result.push_str(&text[rest..token_start]);
+        if token_end > token_start {
+            result.push_str("[REDACTED]");
+        }
+        rest = token_end;
+    }
+    result.push_str(&text[rest..]);
+    result
+}
+
+/// Truncates every `latitude,longitude` pair, such as `47.6062,-122.3321`,
+/// `47.6062, -122` or `40,-105.2705`, to one decimal place (about 11 km). At
+/// least one of the two numbers must have fractional digits, so plain lists
+/// like `1,2` are left alone.
+pub(crate) fn coarsen_coordinates(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        // Only start at the beginning of a number
+        let starts_number = i == 0 || !(bytes[i - 1].is_ascii_digit() || bytes[i - 1] == b'.');
+        if starts_number && let Some(first) = number(bytes, i) {
+            let mut j = first.end;
+            if bytes.get(j) == Some(&b',') {
+                j += 1;
+                if bytes.get(j) == Some(&b' ') {
+                    j += 1;
+                }
+                if let Some(second) = number(bytes, j)
+                    && (first.fractional || second.fractional)
+                {
+                    result.push_str(&text[copied..first.kept]);
+                    result.push_str(&text[first.end..second.kept]);
+                    copied = second.end;
+                    i = second.end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    result.push_str(&text[copied..]);
+    result
+}
+
+struct Number {
+    /// End of the integer part plus the first fractional digit, if any.
+    kept: usize,
+    end: usize,
+    fractional: bool,
+}
+
+/// Matches `-?\d+(\.\d+)?` at `start`.
+fn number(bytes: &[u8], start: usize) -> Option<Number> {
+    let mut i = start;
+    if bytes.get(i) == Some(&b'-') {
+        i += 1;
+    }
+    let integer_start = i;
+    while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        i += 1;
+    }
+    if i == integer_start {
+        return None;
+    }
+    if bytes.get(i) != Some(&b'.') {
+        return Some(Number {
+            kept: i,
+            end: i,
+            fractional: false,
+        });
+    }
+    let fraction_start = i + 1;
+    let mut end = fraction_start;
+    while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+        end += 1;
+    }
+    if end == fraction_start {
+        return None;
+    }
+    Some(Number {
+        kept: fraction_start + 1,
+        end,
+        fractional: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_bearer_tokens_in_any_case() {
+        assert_eq!(
+            redact_bearer_tokens("authorization: Bearer abc.DEF-123_~+/= rest"),
+            "authorization: Bearer [REDACTED] rest"
+        );
+        assert_eq!(
+            redact_bearer_tokens("BEARER a1, bearer b2\""),
+            "BEARER [REDACTED], bearer [REDACTED]\""
+        );
+        assert_eq!(redact_bearer_tokens("token=bearer "), "token=bearer ");
+        assert_eq!(redact_bearer_tokens("no credentials"), "no credentials");
+    }
+
+    #[test]
+    fn coarsens_coordinate_pairs() {
+        assert_eq!(
+            coarsen_coordinates("/points/47.6062,-122.3321 and 47.6062, -122.3321"),
+            "/points/47.6,-122.3 and 47.6, -122.3"
+        );
+        assert_eq!(
+            coarsen_coordinates("/points/47.6062,-122 /points/40,-105.2705"),
+            "/points/47.6,-122 /points/40,-105.2"
+        );
+        assert_eq!(
+            coarsen_coordinates("coordinates=\"-14.2761,-170.6639\""),
+            "coordinates=\"-14.2,-170.6\""
+        );
+    }
+
+    #[test]
+    fn leaves_other_numbers_alone() {
+        for text in ["1,2,3", "version 1.2", "took 12.5ms, 3 retries", "v1.2.3,4"] {
+            assert_eq!(coarsen_coordinates(text), text);
+        }
+    }
+}
@@ -1,24 +1,87 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use anyhow::Result;
-use reqwest;
 use rmcp::{
-    ServerHandler,
-    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    Error as McpError, RoleServer, ServerHandler, ServiceExt,
+    handler::server::{
+        router::tool::ToolRouter,
+        tool::{Parameters, ToolCallContext},
+    },
     model::*,
-    schemars, tool, tool_handler, tool_router,
-    transport::streamable_http_server::{
-        StreamableHttpService, session::local::LocalSessionManager,
+    schemars,
+    service::RequestContext,
+    tool, tool_router,
+    transport::{
+        sse_server::{SseServer, SseServerConfig},
+        stdio,
+        streamable_http_server::{
+            StreamableHttpService,
+            session::local::{LocalSessionManager, SessionConfig},
+        },
     },
 };
 
+use tracing::{Instrument, field::Empty};
 use tracing_subscriber::{
+    Layer,
     layer::SubscriberExt,
     util::SubscriberInitExt,
     {self},
 };
 
-const NWS_API_BASE: &str = "https://api.weather.gov";
-const USER_AGENT: &str = "weather-app/2.0";
-const BIND_ADDRESS: &str = "127.0.0.1:8000";
+mod alerts;
+mod auth;
+mod cache;
+mod circuit_breaker;
+mod config;
+mod coordinates;
+mod error;
+mod gazetteer;
+mod geometry;
+mod health;
+mod logging;
+mod metrics;
+mod points_store;
+mod rate_limit;
+mod resources;
+mod retry;
+mod sessions;
+mod telemetry;
+mod zones;
+
+use alerts::{AlertArea, AlertFilters, MarineArea, Region};
+use auth::Authenticator;
+use bytes::Bytes;
+use cache::{Lookup, ResponseCache, Validators};
+use circuit_breaker::{CircuitBreaker, CircuitStatus};
+use config::{Config, LogFormat, Transport};
+use coordinates::Coordinates;
+use error::{NwsError, ProblemDetails};
+use gazetteer::Gazetteer;
+use geometry::{Geometry, Proximity};
+use health::Health;
+use metrics::Metrics;
+use points_store::PointsStore;
+use rate_limit::{ClientLimiter, UpstreamBudget};
+use resources::{AlertResource, Subscriptions};
+use retry::RetryPolicy;
+use sessions::{SessionLimiter, SessionPermit};
+use zones::{ZoneCache, ZoneResponse};
+
+/// Consecutive upstream failures before requests are short-circuited.
+const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+const CIRCUIT_OPEN_DURATION: Duration = Duration::from_secs(30);
+/// Oldest cached data served when NWS is unavailable.
+const MAX_STALE_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+/// Timeout for the readiness check against the NWS API root.
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MCP_PATH: &str = "/mcp";
+const SSE_PATH: &str = "/sse";
+const SSE_POST_PATH: &str = "/message";
 
 #[derive(Debug, serde::Deserialize)]
 pub struct AlertResponse {
@@ -27,25 +90,47 @@ pub struct AlertResponse {
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Feature {
+    /// Present for polygon-based alerts, null for zone-based ones
+    pub geometry: Option<Geometry>,
     pub properties: FeatureProps,
+    /// Where the requested location lies relative to the alert, if known
+    #[serde(skip)]
+    pub proximity: Option<Proximity>,
 }
 
 #[derive(Debug, serde::Deserialize)]
 pub struct FeatureProps {
+    pub id: String,
     pub event: String,
     #[serde(rename = "areaDesc")]
     pub area_desc: String,
     pub severity: String,
+    pub urgency: String,
+    pub certainty: String,
     pub status: String,
+    #[serde(rename = "messageType")]
+    pub message_type: String,
     pub headline: String,
+    /// URLs of the zones the alert is issued for
+    #[serde(rename = "affectedZones", default)]
+    pub affected_zones: Vec<String>,
+    pub description: Option<String>,
+    pub instruction: Option<String>,
+    pub response: Option<String>,
+    #[serde(rename = "senderName")]
+    pub sender_name: Option<String>,
+    pub sent: Option<String>,
+    pub onset: Option<String>,
+    pub expires: Option<String>,
+    pub ends: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct PointsRequest {
-    #[schemars(description = "latitude of the location in decimal format")]
-    pub latitude: String,
-    #[schemars(description = "longitude of the location in decimal format")]
-    pub longitude: String,
+    #[schemars(description = "latitude of the location in decimal degrees, from -90 to 90")]
+    pub latitude: f64,
+    #[schemars(description = "longitude of the location in decimal degrees, from -180 to 180")]
+    pub longitude: f64,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -53,9 +138,73 @@ pub struct PointsResponse {
     pub properties: PointsProps,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct PointsProps {
+    #[serde(rename = "gridId")]
+    pub grid_id: String,
+    #[serde(rename = "gridX")]
+    pub grid_x: i32,
+    #[serde(rename = "gridY")]
+    pub grid_y: i32,
+    #[serde(rename = "forecastZone")]
+    pub forecast_zone: Option<String>,
+    pub county: Option<String>,
+    #[serde(rename = "fireWeatherZone")]
+    pub fire_weather_zone: Option<String>,
     pub forecast: String,
+    #[serde(rename = "forecastHourly")]
+    pub forecast_hourly: String,
+    #[serde(rename = "observationStations")]
+    pub observation_stations: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StationsResponse {
+    pub features: Vec<StationFeature>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StationFeature {
+    pub geometry: PointGeometry,
+    pub properties: StationProps,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PointGeometry {
+    /// GeoJSON position, `[longitude, latitude]`
+    pub coordinates: Vec<f64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct StationProps {
+    #[serde(rename = "stationIdentifier")]
+    pub station_identifier: String,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ObservationResponse {
+    pub properties: ObservationProps,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ObservationProps {
+    pub timestamp: String,
+    #[serde(rename = "textDescription")]
+    pub text_description: Option<String>,
+    pub temperature: QuantitativeValue,
+    pub dewpoint: QuantitativeValue,
+    #[serde(rename = "relativeHumidity")]
+    pub relative_humidity: QuantitativeValue,
+    #[serde(rename = "windDirection")]
+    pub wind_direction: QuantitativeValue,
+    #[serde(rename = "windSpeed")]
+    pub wind_speed: QuantitativeValue,
+    #[serde(rename = "windGust")]
+    pub wind_gust: QuantitativeValue,
+    #[serde(rename = "barometricPressure")]
+    pub barometric_pressure: QuantitativeValue,
+    pub visibility: QuantitativeValue,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
@@ -71,6 +220,8 @@ pub struct GridPointsProps {
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct Period {
     pub name: String,
+    #[serde(rename = "startTime")]
+    pub start_time: String,
     pub temperature: i32,
     #[serde(rename = "temperatureUnit")]
     pub temperature_unit: String,
@@ -80,22 +231,114 @@ pub struct Period {
     pub wind_direction: String,
     #[serde(rename = "shortForecast")]
     pub short_forecast: String,
+    #[serde(rename = "probabilityOfPrecipitation")]
+    pub probability_of_precipitation: Option<QuantitativeValue>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct QuantitativeValue {
+    pub value: Option<f64>,
+    #[serde(rename = "unitCode")]
+    pub unit_code: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetAlertsRequest {
-    #[schemars(description = "the US state to get alerts for")]
-    pub state: String,
+    #[schemars(
+        description = "US state or territory to get alerts for, as a two-letter code or a name such as \"California\""
+    )]
+    pub state: Option<String>,
+    #[schemars(
+        description = "marine area to get alerts for, covering coastal and offshore waters or one of the Great Lakes"
+    )]
+    pub marine_area: Option<MarineArea>,
+    #[schemars(description = "marine region to get alerts for")]
+    pub region: Option<Region>,
+    #[schemars(
+        description = "NWS forecast, county or marine zone IDs to get alerts for, e.g. [\"WAZ558\", \"PZZ135\"]"
+    )]
+    #[serde(default)]
+    pub zone: Vec<String>,
+    #[serde(flatten)]
+    pub filters: AlertFilters,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetAlertsForLocationRequest {
+    #[schemars(description = "latitude of the location in decimal degrees, from -90 to 90")]
+    pub latitude: Option<f64>,
+    #[schemars(description = "longitude of the location in decimal degrees, from -180 to 180")]
+    pub longitude: Option<f64>,
+    #[schemars(
+        description = "US place name (e.g. \"Seattle, WA\") or 5-digit ZIP code, as an alternative to latitude and longitude"
+    )]
+    pub location: Option<String>,
+    #[schemars(
+        description = "also include alerts within this many kilometers of the location, up to 500"
+    )]
+    pub radius_km: Option<f64>,
+    #[serde(flatten)]
+    pub filters: AlertFilters,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetAlertDetailsRequest {
+    #[schemars(
+        description = "alert ID as listed by the alert tools, e.g. \"urn:oid:2.49.0.1.840.0.1a2b3c\""
+    )]
+    pub id: String,
 }
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct GetForecastRequest {
-    #[schemars(description = "latitude of the location in decimal format")]
-    pub latitude: String,
-    #[schemars(description = "longitude of the location in decimal format")]
-    pub longitude: String,
+    #[schemars(description = "latitude of the location in decimal degrees, from -90 to 90")]
+    pub latitude: Option<f64>,
+    #[schemars(description = "longitude of the location in decimal degrees, from -180 to 180")]
+    pub longitude: Option<f64>,
+    #[schemars(
+        description = "US place name (e.g. \"Seattle, WA\") or 5-digit ZIP code, as an alternative to latitude and longitude"
+    )]
+    pub location: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetHourlyForecastRequest {
+    #[schemars(description = "latitude of the location in decimal degrees, from -90 to 90")]
+    pub latitude: Option<f64>,
+    #[schemars(description = "longitude of the location in decimal degrees, from -180 to 180")]
+    pub longitude: Option<f64>,
+    #[schemars(
+        description = "US place name (e.g. \"Seattle, WA\") or 5-digit ZIP code, as an alternative to latitude and longitude"
+    )]
+    pub location: Option<String>,
+    #[schemars(description = "number of hours to return, from 1 to 156 (defaults to 24)")]
+    pub hours: Option<usize>,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct GetCurrentConditionsRequest {
+    #[schemars(description = "latitude of the location in decimal degrees, from -90 to 90")]
+    pub latitude: Option<f64>,
+    #[schemars(description = "longitude of the location in decimal degrees, from -180 to 180")]
+    pub longitude: Option<f64>,
+    #[schemars(
+        description = "US place name (e.g. \"Seattle, WA\") or 5-digit ZIP code, as an alternative to latitude and longitude"
+    )]
+    pub location: Option<String>,
+}
+
+const DEFAULT_FORECAST_HOURS: usize = 24;
+const MAX_FORECAST_HOURS: usize = 156;
+/// Number of nearby stations to try before giving up on current conditions.
+const MAX_OBSERVATION_STATIONS: usize = 5;
+/// Observations older than this are considered stale.
+const MAX_OBSERVATION_AGE_MINUTES: i64 = 120;
+const MAX_ALERT_RADIUS_KM: f64 = 500.0;
+/// Zone boundaries fetched per call to locate zone-based alerts.
+const MAX_ZONE_FETCHES: usize = 40;
+/// Zone boundaries fetched at once.
+const ZONE_FETCH_CONCURRENCY: usize = 4;
+
 fn format_alerts(alerts: &[Feature]) -> String {
     if alerts.is_empty() {
         return "No active alerts found.".to_string();
@@ -105,13 +348,84 @@ fn format_alerts(alerts: &[Feature]) -> String {
 
     for alert in alerts {
         result.push_str(&format!(
-            "Event: {}\nArea: {}\nSeverity: {}\nStatus: {}\nHeadline: {}\n---\n",
+            "ID: {}\nEvent: {}\nArea: {}\nSeverity: {}\nStatus: {}\nHeadline: {}\n",
+            alert.properties.id,
             alert.properties.event,
             alert.properties.area_desc,
             alert.properties.severity,
             alert.properties.status,
             alert.properties.headline
         ));
+        match alert.proximity {
+            Some(Proximity {
+                inside: true,
+                edge_km,
+            }) => result.push_str(&format!(
+                "Location: inside the alert area, {:.1} km from its edge\n",
+                edge_km
+            )),
+            Some(Proximity { edge_km, .. }) => result.push_str(&format!(
+                "Location: {:.1} km outside the alert area\n",
+                edge_km
+            )),
+            None => {}
+        }
+        result.push_str("---\n");
+    }
+    result
+}
+
+fn format_alert_details(alert: &FeatureProps) -> String {
+    let text = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or("N/A")
+            .to_string()
+    };
+
+    format!(
+        "ID: {}\nEvent: {}\nHeadline: {}\nArea: {}\nSeverity: {}\nUrgency: {}\nCertainty: {}\nResponse: {}\nStatus: {}\nMessage Type: {}\nSender: {}\nSent: {}\nOnset: {}\nExpires: {}\nEnds: {}\n\nDescription:\n{}\n\nInstructions:\n{}\n",
+        alert.id,
+        alert.event,
+        alert.headline,
+        alert.area_desc,
+        alert.severity,
+        alert.urgency,
+        alert.certainty,
+        text(&alert.response),
+        alert.status,
+        alert.message_type,
+        text(&alert.sender_name),
+        text(&alert.sent),
+        text(&alert.onset),
+        text(&alert.expires),
+        text(&alert.ends),
+        text(&alert.description),
+        text(&alert.instruction)
+    )
+}
+
+/// Applies the local filters and limit, noting how many alerts were left out
+/// by the limit.
+fn format_filtered_alerts(alerts: Vec<Feature>, filters: &AlertFilters) -> String {
+    let mut alerts: Vec<Feature> = alerts
+        .into_iter()
+        .filter(|alert| filters.matches(&alert.properties))
+        .collect();
+    let total = alerts.len();
+    if let Some(limit) = filters.limit {
+        alerts.truncate(limit);
+    }
+
+    let mut result = format_alerts(&alerts);
+    if alerts.len() < total {
+        result.push_str(&format!(
+            "Showing {} of {} matching alerts.\n",
+            alerts.len(),
+            total
+        ));
     }
     result
 }
@@ -137,140 +451,1395 @@ fn format_forecast(periods: &[Period]) -> String {
     result
 }
 
+fn format_hourly_forecast(periods: &[Period], hours: usize) -> String {
+    if periods.is_empty() {
+        return "No hourly forecast data available.".to_string();
+    }
+
+    let periods = &periods[..periods.len().min(hours)];
+    let mut result = String::with_capacity((periods.len() + 1) * 80);
+
+    result.push_str("Time             | Temp  | Precip | Wind            | Forecast\n");
+    for period in periods {
+        // startTime is ISO 8601, e.g. "2025-07-10T14:00:00-07:00"
+        let time = period.start_time.get(..16).unwrap_or(&period.start_time);
+        let precip = period
+            .probability_of_precipitation
+            .as_ref()
+            .and_then(|p| p.value)
+            .map(|v| format!("{}%", v.round()))
+            .unwrap_or_else(|| "-".to_string());
+        result.push_str(&format!(
+            "{} | {:>3}°{} | {:>6} | {:<15} | {}\n",
+            time.replacen('T', " ", 1),
+            period.temperature,
+            period.temperature_unit,
+            precip,
+            format!("{} {}", period.wind_speed, period.wind_direction),
+            period.short_forecast
+        ));
+    }
+    result
+}
+
+/// Returns a reason the observation should be skipped in favor of the next station, if any.
+fn unusable_observation(obs: &ObservationProps) -> Option<String> {
+    if obs.temperature.value.is_none() {
+        return Some("temperature is missing".to_string());
+    }
+    match chrono::DateTime::parse_from_rfc3339(&obs.timestamp) {
+        Ok(timestamp) => {
+            let age = chrono::Utc::now().signed_duration_since(timestamp);
+            (age.num_minutes() > MAX_OBSERVATION_AGE_MINUTES)
+                .then(|| format!("observation is {} minutes old", age.num_minutes()))
+        }
+        Err(_) => Some(format!("invalid timestamp {}", obs.timestamp)),
+    }
+}
+
+/// Names of the reported fields, other than temperature, that are null.
+/// Gusts are left out because they are null whenever the wind is steady.
+fn missing_fields(obs: &ObservationProps) -> Vec<&'static str> {
+    [
+        ("dew point", &obs.dewpoint),
+        ("humidity", &obs.relative_humidity),
+        ("wind direction", &obs.wind_direction),
+        ("wind speed", &obs.wind_speed),
+        ("pressure", &obs.barometric_pressure),
+        ("visibility", &obs.visibility),
+    ]
+    .into_iter()
+    .filter(|(_, value)| value.value.is_none())
+    .map(|(name, _)| name)
+    .collect()
+}
+
+fn format_observation(
+    station: &StationProps,
+    distance_km: Option<f64>,
+    obs: &ObservationProps,
+) -> String {
+    // NWS observations are reported in SI units (degC, km/h, Pa, m)
+    let temp = |v: &QuantitativeValue| {
+        v.value
+            .map(|c| format!("{:.0}°F ({:.1}°C)", c * 9.0 / 5.0 + 32.0, c))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+    let speed = |v: &QuantitativeValue| {
+        v.value
+            .map(|kmh| format!("{:.0} mph", kmh * 0.621371))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+
+    let location = match distance_km {
+        Some(km) => format!(
+            "{} ({}), {:.1} mi away",
+            station.name,
+            station.station_identifier,
+            km * 0.621371
+        ),
+        None => format!("{} ({})", station.name, station.station_identifier),
+    };
+    let wind_direction = obs
+        .wind_direction
+        .value
+        .map(|deg| format!(" from {:.0}°", deg))
+        .unwrap_or_default();
+
+    format!(
+        "Station: {}\nObserved: {}\nConditions: {}\nTemperature: {}\nDew Point: {}\nHumidity: {}\nWind: {}{}\nGusts: {}\nPressure: {}\nVisibility: {}\n",
+        location,
+        obs.timestamp,
+        obs.text_description
+            .as_deref()
+            .filter(|d| !d.is_empty())
+            .unwrap_or("N/A"),
+        temp(&obs.temperature),
+        temp(&obs.dewpoint),
+        obs.relative_humidity
+            .value
+            .map(|rh| format!("{:.0}%", rh))
+            .unwrap_or_else(|| "N/A".to_string()),
+        speed(&obs.wind_speed),
+        wind_direction,
+        speed(&obs.wind_gust),
+        obs.barometric_pressure
+            .value
+            .map(|pa| format!("{:.2} inHg ({:.0} hPa)", pa / 3386.389, pa / 100.0))
+            .unwrap_or_else(|| "N/A".to_string()),
+        obs.visibility
+            .value
+            .map(|m| format!("{:.1} mi", m / 1609.344))
+            .unwrap_or_else(|| "N/A".to_string()),
+    )
+}
+
+/// Prepends a warning to output built from a stale copy of NWS data.
+fn with_stale_notice(output: String, stale_age: Option<Duration>) -> String {
+    match stale_age {
+        Some(age) => format!(
+            "Note: the National Weather Service API is currently unavailable. The following data was retrieved {} minutes ago and may be out of date.\n\n{}",
+            age.as_secs() / 60,
+            output
+        ),
+        None => output,
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Weather {
     tool_router: ToolRouter<Self>,
     client: reqwest::Client,
+    api_base: String,
+    cache: Arc<ResponseCache>,
+    points: Arc<PointsStore>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    budget: Arc<UpstreamBudget>,
+    metrics: Arc<Metrics>,
+    zones: Arc<ZoneCache>,
+    subscriptions: Arc<Subscriptions>,
+    /// Distinguishes this handler's subscriptions from other sessions'.
+    subscriber: u64,
+    /// Held for the lifetime of an HTTP session; `None` for the prototype
+    /// handler and for stdio.
+    _session: Option<Arc<SessionPermit>>,
 }
 
 #[tool_router]
 impl Weather {
-    #[allow(dead_code)]
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
             tool_router: Self::tool_router(),
             client: reqwest::Client::builder()
-                .user_agent(USER_AGENT)
+                .user_agent(&config.user_agent)
+                .timeout(config.request_timeout)
                 .build()
                 .expect("Failed to create HTTP client"),
+            api_base: config.nws_api_base.clone(),
+            cache: Arc::new(ResponseCache::new(config.cache_max_bytes)),
+            points: Arc::new(PointsStore::open(&config.data_dir)),
+            retry: config.retry.clone(),
+            breaker: Arc::new(CircuitBreaker::new(
+                CIRCUIT_FAILURE_THRESHOLD,
+                CIRCUIT_OPEN_DURATION,
+            )),
+            budget: Arc::new(UpstreamBudget::new(
+                config.upstream_rate,
+                config.upstream_max_queue,
+            )),
+            metrics: Arc::default(),
+            zones: Arc::default(),
+            subscriptions: Arc::default(),
+            subscriber: resources::next_subscriber_id(),
+            _session: None,
         }
     }
 
-    async fn make_request<T>(&self, url: &str) -> Result<T, String>
-    where
-        T: serde::de::DeserializeOwned,
-    {
-        tracing::info!("Making request to: {}", url);
+    /// A handler for a new session, sharing this one's client and caches.
+    fn for_session(&self, permit: SessionPermit) -> Self {
+        Self {
+            subscriber: resources::next_subscriber_id(),
+            _session: Some(Arc::new(permit)),
+            ..self.clone()
+        }
+    }
+
+    pub fn circuit_status(&self) -> CircuitStatus {
+        self.breaker.status()
+    }
 
+    /// Sends a request to the NWS API root, bypassing the cache and retries,
+    /// to check that NWS is reachable.
+    pub async fn ping_upstream(&self) -> Result<(), NwsError> {
+        self.budget
+            .acquire()
+            .await
+            .map_err(|retry_in| NwsError::RateLimited { retry_in })?;
         let response = self
             .client
-            .get(url)
+            .get(format!("{}/", self.api_base))
+            .timeout(PING_TIMEOUT)
             .send()
+            .await?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(NwsError::Status(status)),
+        }
+    }
+
+    async fn make_request<T>(&self, url: &str) -> Result<T, NwsError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.make_request_with_age(url)
             .await
-            .map_err(|e| format!("Request failed: {}", e))?;
+            .map(|(value, _)| value)
+    }
+
+    /// Like `make_request`, but also returns the age of the data when a stale
+    /// copy was served because NWS is unavailable.
+    async fn make_request_with_age<T>(&self, url: &str) -> Result<(T, Option<Duration>), NwsError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let (body, stale_age) = self.fetch(url).await?;
+        let value = serde_json::from_slice::<T>(&body).map_err(|e| {
+            tracing::error!("Failed to parse response from {}: {}", url, e);
+            NwsError::Decode(e.to_string())
+        })?;
+        Ok((value, stale_age))
+    }
 
-        tracing::info!("Received response: {:?}", response);
+    /// Fetches a response body, serving it from the shared cache when fresh.
+    /// When NWS is failing, falls back to the last known good copy and
+    /// returns its age alongside it.
+    #[tracing::instrument(skip(self))]
+    async fn fetch(&self, url: &str) -> Result<(Bytes, Option<Duration>), NwsError> {
+        let validators = match self.cache.lookup(url) {
+            Lookup::Fresh(body) => return Ok((body, None)),
+            Lookup::Stale(validators) => Some(validators),
+            Lookup::Miss => None,
+        };
+
+        let result = match self.breaker.try_acquire() {
+            Ok(()) => {
+                let result = self.fetch_with_retry(url, validators.as_ref()).await;
+                match &result {
+                    Err(e) if e.is_retryable() => self.breaker.record_failure(),
+                    // Rejected before reaching NWS
+                    Err(NwsError::RateLimited { .. }) => {}
+                    // Any answer from NWS, even a 4xx, means it is up
+                    _ => self.breaker.record_success(),
+                }
+                result
+            }
+            Err(retry_in) => Err(NwsError::Unavailable { retry_in }),
+        };
+
+        match result {
+            Ok(body) => Ok((body, None)),
+            Err(e)
+                if e.is_retryable()
+                    || matches!(
+                        e,
+                        NwsError::Unavailable { .. } | NwsError::RateLimited { .. }
+                    ) =>
+            {
+                match self.cache.last_known_good(url, MAX_STALE_AGE) {
+                    Some((body, age)) => {
+                        tracing::warn!("Serving stale copy of {} ({:?} old): {}", url, age, e);
+                        self.metrics.record_stale_response();
+                        Ok((body, Some(age)))
+                    }
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends the request, retrying transient failures according to the
+    /// retry policy. Stale cache entries are revalidated with a conditional
+    /// request.
+    async fn fetch_with_retry(
+        &self,
+        url: &str,
+        validators: Option<&Validators>,
+    ) -> Result<Bytes, NwsError> {
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            if let Err(retry_in) = self.budget.acquire().await {
+                self.metrics.record_budget_rejection();
+                return Err(NwsError::RateLimited { retry_in });
+            }
+            let result = self
+                .fetch_once(url, validators)
+                .instrument(tracing::info_span!(
+                    "attempt",
+                    attempt,
+                    otel.kind = "client",
+                    http.status_code = Empty,
+                    nws.correlation_id = Empty,
+                ))
+                .await;
+
+            let (error, retry_after) = match result {
+                Ok(body) => return Ok(body),
+                Err(failure) => failure,
+            };
+            if !error.is_retryable() || attempt >= self.retry.max_attempts {
+                return Err(error);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| self.retry.backoff(attempt));
+            if started.elapsed() + delay > self.retry.deadline {
+                tracing::warn!(
+                    "Not retrying {}: waiting {:?} would exceed the retry deadline",
+                    url,
+                    delay
+                );
+                return Err(error);
+            }
+
+            tracing::warn!(
+                "Attempt {} for {} failed: {}; retrying in {:?}",
+                attempt,
+                url,
+                error,
+                delay
+            );
+            self.metrics.record_retry();
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Makes a single request. Failures carry the server's `Retry-After`
+    /// hint, if any.
+    async fn fetch_once(
+        &self,
+        url: &str,
+        validators: Option<&Validators>,
+    ) -> Result<Bytes, (NwsError, Option<Duration>)> {
+        tracing::info!("Making request to: {}", url);
+
+        let mut request = self.client.get(url);
+        if let Some(validators) = validators {
+            request = validators.apply(request);
+        }
+        let started = Instant::now();
+        let response = request.send().await;
+        self.metrics.record_upstream(
+            url,
+            response.as_ref().ok().map(|r| r.status()),
+            started.elapsed(),
+        );
+        let response = response.map_err(|e| {
+            tracing::error!("Request to {} failed: {}", url, e);
+            (NwsError::from(e), None)
+        })?;
+        tracing::Span::current().record("http.status_code", response.status().as_u16());
+
+        tracing::info!(
+            url,
+            status = response.status().as_u16(),
+            latency_ms = started.elapsed().as_millis() as u64,
+            "Received NWS response"
+        );
 
         match response.status() {
-            reqwest::StatusCode::OK => response
-                .json::<T>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e)),
-            status => Err(format!("Request failed with status: {}", status)),
+            reqwest::StatusCode::OK => {
+                let headers = response.headers().clone();
+                let body = response.bytes().await.map_err(|e| (e.into(), None))?;
+                self.cache.store(url, &headers, body.clone());
+                Ok(body)
+            }
+            reqwest::StatusCode::NOT_MODIFIED => {
+                match self.cache.revalidated(url, response.headers()) {
+                    Some(body) => Ok(body),
+                    // Evicted while the request was in flight
+                    None => Err((NwsError::Status(reqwest::StatusCode::NOT_MODIFIED), None)),
+                }
+            }
+            status => {
+                let retry_after = retry::retry_after(response.headers());
+                let problem = response
+                    .json::<ProblemDetails>()
+                    .await
+                    .ok()
+                    .filter(|problem| problem.title.is_some() || problem.detail.is_some());
+                if let Some(correlation_id) =
+                    problem.as_ref().and_then(|p| p.correlation_id.as_deref())
+                {
+                    tracing::Span::current().record("nws.correlation_id", correlation_id);
+                }
+                match &problem {
+                    Some(problem) => tracing::error!(
+                        status = status.as_u16(),
+                        title = problem.title.as_deref(),
+                        detail = problem.detail.as_deref(),
+                        instance = problem.instance.as_deref(),
+                        correlation_id = problem.correlation_id.as_deref(),
+                        "Request to {} failed",
+                        url
+                    ),
+                    None => tracing::error!("Request to {} failed with status {}", url, status),
+                }
+                let error = match problem {
+                    Some(problem) => NwsError::Problem { status, problem },
+                    None => NwsError::Status(status),
+                };
+                Err((error, retry_after))
+            }
+        }
+    }
+
+    /// Looks up the forecast office, grid and related URLs for a point,
+    /// consulting the persistent store before calling `/points`. A stale copy
+    /// is as good as a fresh one here, since grid assignments rarely change.
+    async fn resolve_points(&self, coordinates: &Coordinates) -> Result<PointsProps, NwsError> {
+        if let Some(properties) = self.points.get(coordinates).await {
+            tracing::debug!("Points store hit for {}", coordinates);
+            return Ok(properties);
+        }
+
+        let points_url = format!("{}/points/{}", self.api_base, coordinates);
+        let points = self.make_request::<PointsResponse>(&points_url).await?;
+        self.points
+            .insert(coordinates, points.properties.clone())
+            .await;
+        Ok(points.properties)
+    }
+
+    /// IDs of the forecast, county and fire weather zones a point lies in,
+    /// taken from the last segment of the zone URLs. Empty for points without
+    /// grid data, such as offshore ones, which can still be queried by point.
+    async fn point_zones(&self, coordinates: &Coordinates) -> Vec<String> {
+        match self.resolve_points(coordinates).await {
+            Ok(points) => [
+                points.forecast_zone,
+                points.county,
+                points.fire_weather_zone,
+            ]
+            .into_iter()
+            .flatten()
+            .filter_map(|url| url.rsplit('/').next().map(str::to_string))
+            .collect(),
+            Err(e) => {
+                tracing::warn!("Querying alerts by point only: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The alerts an alert resource currently lists.
+    async fn alert_resource(
+        &self,
+        resource: &AlertResource,
+    ) -> Result<(Vec<Feature>, Option<Duration>), NwsError> {
+        match resource {
+            AlertResource::State(code) => {
+                let url = format!("{}/alerts/active?area={}", self.api_base, code);
+                let (alerts, stale_age) = self.make_request_with_age::<AlertResponse>(&url).await?;
+                Ok((alerts.features, stale_age))
+            }
+            AlertResource::Point(coordinates) => {
+                let zones = self.point_zones(coordinates).await;
+                self.covering_alerts(coordinates, &zones, &AlertFilters::default())
+                    .await
+            }
+        }
+    }
+
+    /// Sorted IDs of the alerts an alert resource currently lists, to tell
+    /// whether it changed, and the age of the data if it is a stale copy.
+    async fn alert_ids(
+        &self,
+        resource: &AlertResource,
+    ) -> Result<(Vec<String>, Option<Duration>), NwsError> {
+        let (alerts, stale_age) = self.alert_resource(resource).await?;
+        let mut ids: Vec<String> = alerts
+            .into_iter()
+            .map(|alert| alert.properties.id)
+            .collect();
+        ids.sort();
+        Ok((ids, stale_age))
+    }
+
+    /// Queries the alerts covering a point: by the point itself and by the
+    /// zones it lies in. The zone query is best effort.
+    async fn covering_alerts(
+        &self,
+        coordinates: &Coordinates,
+        zones: &[String],
+        filters: &AlertFilters,
+    ) -> Result<(Vec<Feature>, Option<Duration>), NwsError> {
+        let point_url = format!(
+            "{}/alerts/active?point={}{}",
+            self.api_base,
+            coordinates,
+            filters.query()
+        );
+        let zone_url = format!(
+            "{}/alerts/active?zone={}{}",
+            self.api_base,
+            zones.join(","),
+            filters.query()
+        );
+        let (by_point, by_zone) = tokio::join!(
+            self.make_request_with_age::<AlertResponse>(&point_url),
+            async {
+                if zones.is_empty() {
+                    return None;
+                }
+                self.make_request_with_age::<AlertResponse>(&zone_url)
+                    .await
+                    .inspect_err(|e| tracing::warn!("Querying alerts by point only: {}", e))
+                    .ok()
+            }
+        );
+        let (by_point, mut stale_age) = by_point?;
+
+        // A zone query also returns polygon alerts that cover only part of
+        // the zone, so those are checked against the point
+        let mut alerts = by_point.features;
+        if let Some((by_zone, zone_stale_age)) = by_zone {
+            let extra: Vec<Feature> = by_zone
+                .features
+                .into_iter()
+                .filter(|alert| {
+                    alert.geometry.as_ref().is_none_or(|geometry| {
+                        geometry.is_empty() || geometry.contains(coordinates)
+                    }) && !alerts
+                        .iter()
+                        .any(|existing| existing.properties.id == alert.properties.id)
+                })
+                .collect();
+            alerts.extend(extra);
+            stale_age = stale_age.max(zone_stale_age);
         }
+        Ok((alerts, stale_age))
     }
 
-    #[tool(description = "Get weather alerts for a US state")]
+    /// Locates a point relative to an alert's polygon or, for a zone-based
+    /// alert, the boundaries of its zones. Returns `None` if a boundary has
+    /// not been fetched by [`Self::fetch_zones`].
+    fn alert_proximity(&self, alert: &Feature, coordinates: &Coordinates) -> Option<Proximity> {
+        if let Some(geometry) = &alert.geometry
+            && !geometry.is_empty()
+        {
+            return geometry::proximity(coordinates, &[geometry]);
+        }
+
+        let boundaries = alert
+            .properties
+            .affected_zones
+            .iter()
+            .map(|url| self.zones.get(url))
+            .collect::<Option<Vec<_>>>()?;
+        let boundaries: Vec<&Geometry> = boundaries.iter().map(AsRef::as_ref).collect();
+        geometry::proximity(coordinates, &boundaries)
+    }
+
+    /// Fetches the uncached zone boundaries of zone-based alerts, up to
+    /// [`MAX_ZONE_FETCHES`] of them and [`ZONE_FETCH_CONCURRENCY`] at a time,
+    /// in the order the alerts come.
+    async fn fetch_zones(&self, alerts: &[Feature]) {
+        let mut urls: Vec<&String> = Vec::new();
+        for url in alerts
+            .iter()
+            .filter(|alert| alert.geometry.as_ref().is_none_or(Geometry::is_empty))
+            .flat_map(|alert| &alert.properties.affected_zones)
+        {
+            if !urls.contains(&url) && self.zones.get(url).is_none() {
+                urls.push(url);
+            }
+        }
+        if urls.len() > MAX_ZONE_FETCHES {
+            tracing::debug!(
+                "Fetching {} of {} zone boundaries",
+                MAX_ZONE_FETCHES,
+                urls.len()
+            );
+            urls.truncate(MAX_ZONE_FETCHES);
+        }
+
+        let permits = Arc::new(tokio::sync::Semaphore::new(ZONE_FETCH_CONCURRENCY));
+        let mut fetches = tokio::task::JoinSet::new();
+        for url in urls {
+            let (weather, url, permits) = (self.clone(), url.clone(), permits.clone());
+            fetches.spawn(
+                async move {
+                    let _permit = permits.acquire().await;
+                    if let Err(e) = weather.zone_geometry(&url).await {
+                        tracing::warn!("Cannot fetch zone boundary {}: {}", url, e);
+                    }
+                }
+                .instrument(tracing::Span::current()),
+            );
+        }
+        fetches.join_all().await;
+    }
+
+    async fn zone_geometry(&self, url: &str) -> Result<Arc<Geometry>, NwsError> {
+        if let Some(boundary) = self.zones.get(url) {
+            return Ok(boundary);
+        }
+        let zone = self.make_request::<ZoneResponse>(url).await?;
+        let boundary = Arc::new(zone.geometry.unwrap_or_default());
+        self.zones.insert(url, boundary.clone());
+        Ok(boundary)
+    }
+
+    /// Resolves the coordinates for a tool call from either explicit
+    /// latitude/longitude or a place name / ZIP code.
+    fn resolve_coordinates(
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+        location: Option<String>,
+    ) -> Result<Coordinates, NwsError> {
+        let coordinates = match (latitude, longitude, location) {
+            (Some(latitude), Some(longitude), _) => Coordinates::new(latitude, longitude),
+            (_, _, Some(location)) => {
+                let place = Gazetteer::bundled()
+                    .lookup(&location)
+                    .map_err(|e| NwsError::InvalidInput(e.to_string()))?;
+                tracing::info!("Resolved location '{}' to {}", location, place);
+                Coordinates::new(place.latitude, place.longitude)
+            }
+            _ => Err(
+                "Provide either both latitude and longitude, or a location such as \"Seattle, WA\" or \"98101\"."
+                    .to_string(),
+            ),
+        };
+        let coordinates = coordinates.map_err(NwsError::InvalidInput)?;
+        tracing::Span::current().record("coordinates", coordinates.to_string());
+        Ok(coordinates)
+    }
+
+    #[tool(
+        description = "Get weather alerts for a US state, marine area, marine region or NWS zones; give exactly one"
+    )]
     async fn get_alerts(
         &self,
-        Parameters(GetAlertsRequest { state }): Parameters<GetAlertsRequest>,
-    ) -> String {
-        tracing::info!("Received request for weather alerts in state: {}", state);
+        Parameters(GetAlertsRequest {
+            state,
+            marine_area,
+            region,
+            zone,
+            filters,
+        }): Parameters<GetAlertsRequest>,
+    ) -> Result<String, NwsError> {
+        let area =
+            AlertArea::new(state, marine_area, region, zone).map_err(NwsError::InvalidInput)?;
+        filters.validate().map_err(NwsError::InvalidInput)?;
 
-        let url = format!("{}/alerts/active?area={}", NWS_API_BASE, state);
+        tracing::info!("Received request for weather alerts in {}", area);
 
-        match self.make_request::<AlertResponse>(&url).await {
-            Ok(alerts) => format_alerts(&alerts.features),
-            Err(e) => {
-                tracing::error!("Failed to fetch alerts: {}", e);
-                "No alerts found or an error occurred.".to_string()
+        let url = format!(
+            "{}/alerts/active?{}{}",
+            self.api_base,
+            area.query(),
+            filters.query()
+        );
+
+        let (alerts, stale_age) = self.make_request_with_age::<AlertResponse>(&url).await?;
+        Ok(with_stale_notice(
+            format_filtered_alerts(alerts.features, &filters),
+            stale_age,
+        ))
+    }
+
+    #[tool(
+        description = "Get the full text of a weather alert by ID, including its description, instructions and timing"
+    )]
+    async fn get_alert_details(
+        &self,
+        Parameters(GetAlertDetailsRequest { id }): Parameters<GetAlertDetailsRequest>,
+    ) -> Result<String, NwsError> {
+        // Also accept the alert's URL, which NWS uses as its GeoJSON ID
+        let id = id.trim().rsplit('/').next().unwrap_or_default();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ":.-_".contains(c))
+        {
+            return Err(NwsError::InvalidInput(format!(
+                "Invalid alert ID \"{}\": expected an ID such as \"urn:oid:2.49.0.1.840.0.1a2b3c\".",
+                id
+            )));
+        }
+
+        tracing::info!("Received request for alert details: {}", id);
+
+        let url = format!("{}/alerts/{}", self.api_base, id);
+        let (alert, stale_age) = self.make_request_with_age::<Feature>(&url).await?;
+        Ok(with_stale_notice(
+            format_alert_details(&alert.properties),
+            stale_age,
+        ))
+    }
+
+    #[tool(
+        description = "Get active weather alerts covering a location, or within a radius of it, using latitude and longitude coordinates or a US place name / ZIP code"
+    )]
+    async fn get_alerts_for_location(
+        &self,
+        Parameters(GetAlertsForLocationRequest {
+            latitude,
+            longitude,
+            location,
+            radius_km,
+            filters,
+        }): Parameters<GetAlertsForLocationRequest>,
+    ) -> Result<String, NwsError> {
+        let coordinates = Self::resolve_coordinates(latitude, longitude, location)?;
+        filters.validate().map_err(NwsError::InvalidInput)?;
+        if let Some(radius) = radius_km
+            && !(0.0..=MAX_ALERT_RADIUS_KM).contains(&radius)
+        {
+            return Err(NwsError::InvalidInput(format!(
+                "Invalid radius_km {}: must be between 0 and {}.",
+                radius, MAX_ALERT_RADIUS_KM
+            )));
+        }
+
+        tracing::info!(
+            "Received coordinates: {}, radius_km = {:?}",
+            coordinates,
+            radius_km
+        );
+
+        let zones = self.point_zones(&coordinates).await;
+        let (mut alerts, mut stale_age) =
+            self.covering_alerts(&coordinates, &zones, &filters).await?;
+        let covering: Vec<String> = alerts
+            .iter()
+            .map(|alert| alert.properties.id.clone())
+            .collect();
+
+        // Alerts near the point may be for other zones, so a radius query also
+        // covers every state and marine area within reach
+        let areas =
+            radius_km.map_or_else(Vec::new, |radius| alerts::areas_near(&coordinates, radius));
+        if !areas.is_empty() {
+            let url = format!(
+                "{}/alerts/active?area={}{}",
+                self.api_base,
+                areas.join(","),
+                filters.query()
+            );
+            let (nearby, nearby_stale_age) =
+                self.make_request_with_age::<AlertResponse>(&url).await?;
+            let nearby: Vec<Feature> = nearby
+                .features
+                .into_iter()
+                .filter(|alert| {
+                    !alerts
+                        .iter()
+                        .any(|existing| existing.properties.id == alert.properties.id)
+                })
+                .collect();
+            alerts.extend(nearby);
+            stale_age = stale_age.max(nearby_stale_age);
+        }
+        alerts.retain(|alert| filters.matches(&alert.properties));
+
+        self.fetch_zones(&alerts).await;
+        for alert in &mut alerts {
+            alert.proximity = self.alert_proximity(alert, &coordinates);
+        }
+        // Alerts that may or may not be within the radius
+        let mut unlocated = Vec::new();
+        if let Some(radius) = radius_km {
+            // NWS says the covering alerts cover the point, whatever the
+            // boundaries say. Without a boundary, a zone-based alert for one
+            // of the point's own zones covers it too
+            let (kept, rest): (Vec<Feature>, Vec<Feature>) =
+                alerts.into_iter().partition(|alert| {
+                    covering.contains(&alert.properties.id)
+                        || alert
+                            .proximity
+                            .is_some_and(|proximity| proximity.within(radius))
+                        || alert.proximity.is_none()
+                            && alert.properties.affected_zones.iter().any(|url| {
+                                zones
+                                    .iter()
+                                    .any(|zone| url.rsplit('/').next() == Some(zone))
+                            })
+                });
+            alerts = kept;
+            unlocated = rest
+                .into_iter()
+                .filter(|alert| alert.proximity.is_none())
+                .collect();
+            // Alerts covering the point first, then the nearest
+            alerts.sort_by(|a, b| {
+                let key = |alert: &Feature| match alert.proximity {
+                    Some(Proximity { inside: true, .. }) => -1.0,
+                    Some(proximity) => proximity.edge_km,
+                    None => f64::MAX,
+                };
+                key(a).total_cmp(&key(b))
+            });
+        }
+
+        let mut result = format_filtered_alerts(alerts, &filters);
+        if !unlocated.is_empty() {
+            result.push_str(&format!(
+                "Alerts nearby that could not be located, which may also be within {} km:\n",
+                radius_km.unwrap_or_default()
+            ));
+            for alert in &unlocated {
+                result.push_str(&format!(
+                    "- {} ({})\n",
+                    alert.properties.event, alert.properties.id
+                ));
             }
         }
+        Ok(with_stale_notice(result, stale_age))
     }
 
-    #[tool(description = "Get forecast using latitude and longitude coordinates")]
+    #[tool(
+        description = "Get forecast using latitude and longitude coordinates or a US place name / ZIP code"
+    )]
     async fn get_forecast(
         &self,
         Parameters(GetForecastRequest {
             latitude,
             longitude,
+            location,
         }): Parameters<GetForecastRequest>,
-    ) -> String {
-        tracing::info!(
-            "Received coordinates: latitude = {}, longitude = {}",
-            latitude,
-            longitude
-        );
+    ) -> Result<String, NwsError> {
+        let coordinates = Self::resolve_coordinates(latitude, longitude, location)?;
 
-        let points_url = format!("{}/points/{},{}", NWS_API_BASE, latitude, longitude);
+        tracing::info!("Received coordinates: {}", coordinates);
 
         // Get the forecast URL
-        let points_result = self.make_request::<PointsResponse>(&points_url).await;
-
-        let points = match points_result {
-            Ok(points) => points,
-            Err(e) => {
-                tracing::error!("Failed to fetch points: {}", e);
-                return "No forecast found or an error occurred.".to_string();
-            }
-        };
+        let points = self.resolve_points(&coordinates).await?;
 
         // Get the forecast data
-        match self
-            .make_request::<GridPointsResponse>(&points.properties.forecast)
-            .await
-        {
-            Ok(forecast) => format_forecast(&forecast.properties.periods),
-            Err(e) => {
-                tracing::error!("Failed to fetch forecast: {}", e);
-                "No forecast found or an error occurred.".to_string()
+        let (forecast, stale_age) = self
+            .make_request_with_age::<GridPointsResponse>(&points.forecast)
+            .await?;
+        Ok(with_stale_notice(
+            format_forecast(&forecast.properties.periods),
+            stale_age,
+        ))
+    }
+
+    #[tool(
+        description = "Get an hour-by-hour forecast using latitude and longitude coordinates or a US place name / ZIP code"
+    )]
+    async fn get_hourly_forecast(
+        &self,
+        Parameters(GetHourlyForecastRequest {
+            latitude,
+            longitude,
+            location,
+            hours,
+        }): Parameters<GetHourlyForecastRequest>,
+    ) -> Result<String, NwsError> {
+        let coordinates = Self::resolve_coordinates(latitude, longitude, location)?;
+        let hours = hours
+            .unwrap_or(DEFAULT_FORECAST_HOURS)
+            .clamp(1, MAX_FORECAST_HOURS);
+
+        tracing::info!("Received coordinates: {}, hours = {}", coordinates, hours);
+
+        // Get the hourly forecast URL
+        let points = self.resolve_points(&coordinates).await?;
+
+        // Get the hourly forecast data
+        let (forecast, stale_age) = self
+            .make_request_with_age::<GridPointsResponse>(&points.forecast_hourly)
+            .await?;
+        Ok(with_stale_notice(
+            format_hourly_forecast(&forecast.properties.periods, hours),
+            stale_age,
+        ))
+    }
+
+    #[tool(
+        description = "Get current observed weather conditions from the nearest station using latitude and longitude coordinates or a US place name / ZIP code"
+    )]
+    async fn get_current_conditions(
+        &self,
+        Parameters(GetCurrentConditionsRequest {
+            latitude,
+            longitude,
+            location,
+        }): Parameters<GetCurrentConditionsRequest>,
+    ) -> Result<String, NwsError> {
+        let coordinates = Self::resolve_coordinates(latitude, longitude, location)?;
+
+        tracing::info!("Received coordinates: {}", coordinates);
+
+        // Get the observation stations URL
+        let points = self.resolve_points(&coordinates).await?;
+
+        let (stations, stations_stale_age) = self
+            .make_request_with_age::<StationsResponse>(&points.observation_stations)
+            .await?;
+        let stations = stations.features;
+
+        // Order stations by distance from the requested point
+        let mut stations: Vec<(Option<f64>, StationFeature)> = stations
+            .into_iter()
+            .map(|station| {
+                let distance = match station.geometry.coordinates.as_slice() {
+                    [longitude, latitude, ..] => Some(coordinates.distance_km(&Coordinates {
+                        latitude: *latitude,
+                        longitude: *longitude,
+                    })),
+                    _ => None,
+                };
+                (distance, station)
+            })
+            .collect();
+        stations.sort_by(|(a, _), (b, _)| a.unwrap_or(f64::MAX).total_cmp(&b.unwrap_or(f64::MAX)));
+
+        // Only report a failure if no station returned anything at all
+        let mut last_error = None;
+        let mut skipped = 0;
+        // The most complete observation so far, by number of missing fields
+        let mut best: Option<(usize, String)> = None;
+
+        for (distance, station) in stations.iter().take(MAX_OBSERVATION_STATIONS) {
+            let station_id = &station.properties.station_identifier;
+            let url = format!(
+                "{}/stations/{}/observations/latest",
+                self.api_base, station_id
+            );
+
+            match self
+                .make_request_with_age::<ObservationResponse>(&url)
+                .await
+            {
+                Ok((observation, stale_age)) => match unusable_observation(&observation.properties)
+                {
+                    None => {
+                        let missing = missing_fields(&observation.properties);
+                        let output = with_stale_notice(
+                            format_observation(
+                                &station.properties,
+                                *distance,
+                                &observation.properties,
+                            ),
+                            stale_age.max(stations_stale_age),
+                        );
+                        if missing.is_empty() {
+                            return Ok(output);
+                        }
+                        tracing::info!(
+                            "Station {} is missing {}; trying the next station",
+                            station_id,
+                            missing.join(", ")
+                        );
+                        if best
+                            .as_ref()
+                            .is_none_or(|(fewest, ..)| missing.len() < *fewest)
+                        {
+                            best = Some((missing.len(), output));
+                        }
+                    }
+                    Some(reason) => {
+                        tracing::warn!("Skipping station {}: {}", station_id, reason);
+                        skipped += 1;
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Failed to fetch observation for {}: {}", station_id, e);
+                    last_error = Some(e);
+                }
             }
         }
+
+        if let Some((_, output)) = best {
+            return Ok(output);
+        }
+        match last_error {
+            Some(e) if skipped == 0 => Err(e),
+            _ => Ok(with_stale_notice(
+                "No recent observations available from nearby stations.".to_string(),
+                stations_stale_age,
+            )),
+        }
+    }
+}
+
+/// A server span for an MCP request, exported as `<method> <target>`. It
+/// continues the client's trace if the HTTP request that carried the MCP
+/// request had a `traceparent` header.
+fn request_span(method: &str, target: &str, context: &RequestContext<RoleServer>) -> tracing::Span {
+    let parts = context.extensions.get::<axum::http::request::Parts>();
+    let header = |name| {
+        parts
+            .and_then(|parts| parts.headers.get(name))
+            .and_then(|value| value.to_str().ok())
+    };
+    // Legacy SSE clients carry the session in the query string
+    let session_id = header("mcp-session-id")
+        .or_else(|| {
+            parts?
+                .uri
+                .query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("sessionId="))
+        })
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "mcp_request",
+        otel.name = format!("{} {}", method, target).trim_end(),
+        otel.kind = "server",
+        method,
+        session_id,
+        tool = Empty,
+        uri = Empty,
+        coordinates = Empty,
+        traceparent = Empty,
+        otel.status_code = Empty,
+    );
+    if let Some(traceparent) = header("traceparent") {
+        span.record("traceparent", traceparent);
     }
+    span
+}
+
+/// Runs an MCP request handler in `span`, marking the span failed if the
+/// handler returns an error.
+async fn traced<T>(
+    span: tracing::Span,
+    handler: impl Future<Output = Result<T, McpError>>,
+) -> Result<T, McpError> {
+    let result = handler.instrument(span.clone()).await;
+    if result.is_err() {
+        span.record("otel.status_code", "ERROR");
+    }
+    result
 }
 
-#[tool_handler]
 impl ServerHandler for Weather {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some("A simple weather forecaster".into()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .build(),
             ..Default::default()
         }
     }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        // Unknown names are lumped together to keep metric labels bounded
+        let tool = match self.tool_router.has_route(&request.name) {
+            true => request.name.to_string(),
+            false => "unknown".to_string(),
+        };
+        let span = request_span("tools/call", &tool, &context);
+        span.record("tool", &tool);
+
+        let started = Instant::now();
+        let result = self
+            .tool_router
+            .call(ToolCallContext::new(self, request, context))
+            .instrument(span.clone())
+            .await;
+        let outcome = match &result {
+            Ok(result) if result.is_error == Some(true) => "error",
+            Ok(_) => "ok",
+            Err(_) => "invalid",
+        };
+        if outcome != "ok" {
+            span.record("otel.status_code", "ERROR");
+        }
+        span.in_scope(|| {
+            tracing::info!(
+                outcome,
+                latency_ms = started.elapsed().as_millis() as u64,
+                "Tool call finished"
+            )
+        });
+        self.metrics
+            .record_tool_call(&tool, outcome, started.elapsed());
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let span = request_span("tools/list", "", &context);
+        traced(span, async {
+            Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+        })
+        .await
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let span = request_span("resources/list", "", &context);
+        traced(span, async {
+            Ok(ListResourcesResult::with_all_items(resources::resources()))
+        })
+        .await
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let span = request_span("resources/templates/list", "", &context);
+        traced(span, async {
+            Ok(ListResourceTemplatesResult::with_all_items(
+                resources::templates(),
+            ))
+        })
+        .await
+    }
+
+    async fn read_resource(
+        &self,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let span = request_span("resources/read", &uri, &context);
+        span.record("uri", &uri);
+        traced(span, async {
+            let resource =
+                AlertResource::parse(&uri).map_err(|e| McpError::resource_not_found(e, None))?;
+            let (alerts, stale_age) = self
+                .alert_resource(&resource)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri,
+                    mime_type: Some(resources::MIME_TYPE.to_string()),
+                    text: with_stale_notice(format_alerts(&alerts), stale_age),
+                }],
+            })
+        })
+        .await
+    }
+
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let span = request_span("resources/subscribe", &uri, &context);
+        span.record("uri", &uri);
+        traced(span, async {
+            let resource =
+                AlertResource::parse(&uri).map_err(|e| McpError::resource_not_found(e, None))?;
+            // The alerts active now are what later polls compare against,
+            // unless only a stale copy is available
+            let alert_ids = self
+                .alert_ids(&resource)
+                .await
+                .inspect_err(|e| tracing::warn!("Cannot read {} on subscribe: {}", uri, e))
+                .ok()
+                .and_then(|(alert_ids, stale_age)| stale_age.is_none().then_some(alert_ids));
+            tracing::info!("Subscribed to {}", uri);
+            self.subscriptions
+                .subscribe(resource, uri, self.subscriber, context.peer, alert_ids)
+                .map_err(|e| McpError::invalid_request(e, None))
+        })
+        .await
+    }
+
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let span = request_span("resources/unsubscribe", &uri, &context);
+        span.record("uri", &uri);
+        traced(span, async {
+            let resource =
+                AlertResource::parse(&uri).map_err(|e| McpError::resource_not_found(e, None))?;
+            self.subscriptions.unsubscribe(&resource, self.subscriber);
+            Ok(())
+        })
+        .await
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing subscriber for logging
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "debug".to_string().into()),
+    let config = Config::load().map_err(anyhow::Error::msg)?;
+
+    // Initialize tracing subscriber for logging. In stdio mode stdout carries
+    // the JSON-RPC stream, so logs must go to stderr.
+    let log_to_stderr = config.transport == Transport::Stdio;
+    let writer = logging::Redacting::new(
+        move || -> Box<dyn std::io::Write> {
+            if log_to_stderr {
+                Box::new(std::io::stderr())
+            } else {
+                Box::new(std::io::stdout())
+            }
+        },
+        config.log_redact_coordinates,
+    );
+    let fmt_layer = match config.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().with_writer(writer).boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .event_format(logging::JsonFormat)
+            .fmt_fields(logging::JsonFields)
+            .with_writer(writer)
+            .boxed(),
+    };
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&config.log_level));
+    // Only this crate's spans are exported, independent of the log level
+    let otlp = config.otlp_endpoint.as_deref().map(|endpoint| {
+        telemetry::OtlpLayer::new(endpoint, config.log_redact_coordinates).with_filter(
+            tracing_subscriber::filter::filter_fn(|metadata| {
+                metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+            }),
         )
-        .with(tracing_subscriber::fmt::layer())
+    });
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(filter))
+        .with(otlp)
         .init();
+    if let Some(endpoint) = &config.otlp_endpoint {
+        tracing::info!("Exporting traces to {}", endpoint);
+    }
 
-    let service = StreamableHttpService::new(
-        || Ok(Weather::new()),
-        LocalSessionManager::default().into(),
-        Default::default(),
-    );
+    // Sessions share one handler so they also share the HTTP client and cache
+    let weather = Weather::new(&config);
+    tokio::spawn(resources::poll(weather.clone()));
+
+    match config.transport {
+        Transport::Stdio => serve_stdio(weather).await,
+        Transport::Http | Transport::Sse => serve_http(&config, weather).await,
+    }
+}
 
-    let router = axum::Router::new().nest_service("/mcp", service);
-    let tcp_listener = tokio::net::TcpListener::bind(BIND_ADDRESS).await?;
-    let _ = axum::serve(tcp_listener, router)
-        .with_graceful_shutdown(async { tokio::signal::ctrl_c().await.unwrap() })
-        .await;
+async fn serve_stdio(weather: Weather) -> anyhow::Result<()> {
+    tracing::info!("Serving MCP over stdio");
+    let service = weather.serve(stdio()).await?;
+    service.waiting().await?;
     Ok(())
 }
+
+/// Serves the HTTP transports: streamable HTTP on `/mcp` for
+/// `Transport::Http`, and legacy HTTP+SSE on `/sse` for both.
+async fn serve_http(config: &Config, weather: Weather) -> anyhow::Result<()> {
+    let auth = Authenticator::new(&config.auth, config.bind_address).map_err(anyhow::Error::msg)?;
+    let limiter = SessionLimiter::new(config.max_sessions);
+
+    let mut router = axum::Router::new();
+    let mut session_manager = None;
+    let transports: &'static [&'static str] = match config.transport {
+        Transport::Http => &["streamable-http", "sse"],
+        _ => &["sse"],
+    };
+    if config.transport == Transport::Http {
+        // Without a keep-alive a session only ends on DELETE, so clients that
+        // just go away would hold their slot under max_sessions forever
+        let manager = Arc::new(LocalSessionManager {
+            sessions: Default::default(),
+            session_config: SessionConfig {
+                keep_alive: Some(config.session_idle_timeout),
+                ..Default::default()
+            },
+        });
+        let streamable = StreamableHttpService::new(
+            {
+                let weather = weather.clone();
+                let limiter = limiter.clone();
+                move || Ok(weather.for_session(limiter.acquire()))
+            },
+            manager.clone(),
+            Default::default(),
+        );
+        router = router.nest_service(MCP_PATH, streamable);
+        session_manager = Some(manager);
+    }
+
+    // Older clients that only speak the 2024-11-05 HTTP+SSE transport
+    let (sse_server, sse_router) = sse_server(config);
+    let ct = {
+        let weather = weather.clone();
+        let limiter = limiter.clone();
+        sse_server.with_service(move || weather.for_session(limiter.acquire()))
+    };
+
+    let router = router
+        .merge(sse_router)
+        .layer(axum::middleware::from_fn_with_state(
+            limiter.clone(),
+            sessions::reject_when_full,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            ClientLimiter::new(config.client_rate),
+            rate_limit::limit_clients,
+        ));
+    let metrics = metrics::router(metrics::Sources {
+        weather: weather.clone(),
+        session_manager,
+        session_limiter: limiter,
+    });
+    let health = Health::new(weather, config.readiness_check_upstream, transports);
+    let router = with_auth(router, auth, config.client_rate)
+        .merge(health::router(Arc::new(health)))
+        .merge(metrics);
+
+    let tcp_listener = tokio::net::TcpListener::bind(config.bind_address).await?;
+    if config.transport == Transport::Http {
+        tracing::info!(
+            "Serving streamable HTTP on http://{}{MCP_PATH}",
+            config.bind_address
+        );
+    }
+    tracing::info!(
+        "Serving HTTP+SSE on http://{}{SSE_PATH}",
+        config.bind_address
+    );
+    let _ = axum::serve(
+        tcp_listener,
+        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        tokio::signal::ctrl_c().await.unwrap();
+        ct.cancel();
+    })
+    .await;
+    Ok(())
+}
+
+/// Requires a bearer token on every route of `router` and publishes the
+/// protected-resource metadata, if authentication is configured. Failed
+/// attempts are limited to `rate` per IP address.
+fn with_auth(
+    router: axum::Router,
+    auth: Option<Arc<Authenticator>>,
+    rate: rate_limit::Rate,
+) -> axum::Router {
+    let Some(auth) = auth else {
+        return router;
+    };
+    let metadata = axum::routing::get(auth::protected_resource_metadata);
+    router
+        .layer(axum::middleware::from_fn_with_state(
+            auth.clone(),
+            auth::require_bearer,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            ClientLimiter::new(rate),
+            rate_limit::limit_failed_auth,
+        ))
+        .merge(
+            axum::Router::new()
+                .route(auth::METADATA_PATH, metadata.clone())
+                .route(&format!("{}{MCP_PATH}", auth::METADATA_PATH), metadata)
+                .with_state(auth),
+        )
+}
+
+fn sse_server(config: &Config) -> (SseServer, axum::Router) {
+    SseServer::new(SseServerConfig {
+        bind: config.bind_address,
+        sse_path: SSE_PATH.to_string(),
+        post_path: SSE_POST_PATH.to_string(),
+        ct: Default::default(),
+        sse_keep_alive: None,
+    })
+}
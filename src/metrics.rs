@@ -0,0 +1,375 @@
+//! Prometheus metrics, rendered in the text exposition format on `/metrics`.
+//!
+//! Tool calls and upstream requests are recorded here as they happen. Cache,
+//! circuit breaker and session figures are read from their owners when the
+//! metrics are scraped.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+
+use crate::{Weather, circuit_breaker::CircuitStatus, sessions::SessionLimiter};
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last is `+Inf`.
+    counts: [u64; BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: Duration) {
+        let secs = value.as_secs_f64();
+        let bucket = BUCKETS
+            .iter()
+            .position(|le| secs <= *le)
+            .unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += secs;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    /// By tool and outcome.
+    tool_calls: BTreeMap<(String, &'static str), u64>,
+    tool_duration: BTreeMap<String, Histogram>,
+    /// By endpoint family and status class.
+    upstream_requests: BTreeMap<(&'static str, &'static str), u64>,
+    upstream_duration: BTreeMap<&'static str, Histogram>,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    recorded: Mutex<Recorded>,
+    retries: AtomicU64,
+    stale_responses: AtomicU64,
+    budget_rejections: AtomicU64,
+}
+
+impl Metrics {
+    /// Records a finished tool call. `outcome` is `ok`, `error` for a tool
+    /// error result, or `invalid` for a call rejected by the protocol layer.
+    pub fn record_tool_call(&self, tool: &str, outcome: &'static str, duration: Duration) {
+        let mut recorded = self.recorded.lock().unwrap();
+        *recorded
+            .tool_calls
+            .entry((tool.to_string(), outcome))
+            .or_default() += 1;
+        recorded
+            .tool_duration
+            .entry(tool.to_string())
+            .or_default()
+            .observe(duration);
+    }
+
+    /// Records one request to NWS; `status` is `None` if no response arrived.
+    pub fn record_upstream(
+        &self,
+        url: &str,
+        status: Option<reqwest::StatusCode>,
+        duration: Duration,
+    ) {
+        let family = endpoint_family(url);
+        let class = match status.map(|s| s.as_u16()) {
+            Some(100..=199) => "1xx",
+            Some(200..=299) => "2xx",
+            Some(300..=399) => "3xx",
+            Some(400..=499) => "4xx",
+            Some(_) => "5xx",
+            None => "error",
+        };
+        let mut recorded = self.recorded.lock().unwrap();
+        *recorded
+            .upstream_requests
+            .entry((family, class))
+            .or_default() += 1;
+        recorded
+            .upstream_duration
+            .entry(family)
+            .or_default()
+            .observe(duration);
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_stale_response(&self) {
+        self.stale_responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_budget_rejection(&self) {
+        self.budget_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Groups NWS URLs by the first path segment, so that label cardinality stays
+/// bounded.
+fn endpoint_family(url: &str) -> &'static str {
+    let path = reqwest::Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_default();
+    match path.trim_start_matches('/').split('/').next() {
+        Some("points") => "points",
+        Some("gridpoints") => "gridpoints",
+        Some("alerts") => "alerts",
+        Some("stations") => "stations",
+        Some("zones") => "zones",
+        Some("") => "root",
+        _ => "other",
+    }
+}
+
+/// What `/metrics` reads from at scrape time.
+#[derive(Debug, Clone)]
+pub struct Sources {
+    pub weather: Weather,
+    /// Streamable HTTP sessions, if that transport is enabled.
+    pub session_manager: Option<Arc<LocalSessionManager>>,
+    pub session_limiter: Arc<SessionLimiter>,
+}
+
+pub fn router(sources: Sources) -> Router {
+    Router::new()
+        .route("/metrics", get(render))
+        .with_state(sources)
+}
+
+async fn render(State(sources): State<Sources>) -> Response {
+    let weather = &sources.weather;
+    let mut out = String::new();
+
+    {
+        let recorded = weather.metrics.recorded.lock().unwrap();
+
+        describe(
+            &mut out,
+            "mcp_tool_calls_total",
+            "counter",
+            "MCP tool calls by tool and outcome.",
+        );
+        for ((tool, outcome), count) in &recorded.tool_calls {
+            let _ = writeln!(
+                out,
+                "mcp_tool_calls_total{{tool=\"{}\",outcome=\"{}\"}} {}",
+                escape(tool),
+                outcome,
+                count
+            );
+        }
+        describe(
+            &mut out,
+            "mcp_tool_call_duration_seconds",
+            "histogram",
+            "MCP tool call latency.",
+        );
+        for (tool, histogram) in &recorded.tool_duration {
+            write_histogram(
+                &mut out,
+                "mcp_tool_call_duration_seconds",
+                &format!("tool=\"{}\"", escape(tool)),
+                histogram,
+            );
+        }
+
+        describe(
+            &mut out,
+            "nws_requests_total",
+            "counter",
+            "Requests sent to the NWS API by endpoint family and status class.",
+        );
+        for ((family, class), count) in &recorded.upstream_requests {
+            let _ = writeln!(
+                out,
+                "nws_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                family, class, count
+            );
+        }
+        describe(
+            &mut out,
+            "nws_request_duration_seconds",
+            "histogram",
+            "NWS API request latency until response headers arrive.",
+        );
+        for (family, histogram) in &recorded.upstream_duration {
+            write_histogram(
+                &mut out,
+                "nws_request_duration_seconds",
+                &format!("endpoint=\"{}\"", family),
+                histogram,
+            );
+        }
+    }
+
+    let metrics = &weather.metrics;
+    counter(
+        &mut out,
+        "nws_retries_total",
+        "NWS requests retried after a transient failure.",
+        metrics.retries.load(Ordering::Relaxed),
+    );
+    counter(
+        &mut out,
+        "nws_stale_responses_total",
+        "Stale cached responses served because NWS was unavailable.",
+        metrics.stale_responses.load(Ordering::Relaxed),
+    );
+    counter(
+        &mut out,
+        "nws_budget_rejections_total",
+        "NWS requests rejected because the outbound request budget was exhausted.",
+        metrics.budget_rejections.load(Ordering::Relaxed),
+    );
+
+    let cache = &weather.cache;
+    let (hits, misses) = (cache.hits(), cache.misses());
+    counter(
+        &mut out,
+        "nws_cache_hits_total",
+        "Response cache lookups answered from cache.",
+        hits,
+    );
+    counter(
+        &mut out,
+        "nws_cache_misses_total",
+        "Response cache lookups that needed a request to NWS.",
+        misses,
+    );
+    counter(
+        &mut out,
+        "nws_cache_revalidations_total",
+        "Stale cache entries confirmed unchanged by NWS.",
+        cache.revalidations(),
+    );
+    let ratio = if hits + misses == 0 {
+        0.0
+    } else {
+        hits as f64 / (hits + misses) as f64
+    };
+    gauge(
+        &mut out,
+        "nws_cache_hit_ratio",
+        "Fraction of response cache lookups answered from cache since startup.",
+        ratio,
+    );
+    gauge(
+        &mut out,
+        "nws_cache_size_bytes",
+        "Total size of cached response bodies.",
+        cache.size() as f64,
+    );
+
+    let breaker = &weather.breaker;
+    let state = match breaker.status() {
+        CircuitStatus::Closed => 0.0,
+        CircuitStatus::HalfOpen => 1.0,
+        CircuitStatus::Open => 2.0,
+    };
+    gauge(
+        &mut out,
+        "nws_circuit_state",
+        "NWS circuit breaker state: 0 closed, 1 half-open, 2 open.",
+        state,
+    );
+    counter(
+        &mut out,
+        "nws_circuit_opened_total",
+        "Times the NWS circuit breaker opened.",
+        breaker.opened(),
+    );
+    counter(
+        &mut out,
+        "nws_circuit_rejections_total",
+        "Requests short-circuited while the NWS circuit breaker was open.",
+        breaker.rejections(),
+    );
+
+    if let Some(manager) = &sources.session_manager {
+        gauge(
+            &mut out,
+            "mcp_streamable_http_sessions",
+            "Active streamable HTTP sessions.",
+            manager.sessions.read().await.len() as f64,
+        );
+    }
+    gauge(
+        &mut out,
+        "mcp_sessions_active",
+        "Active MCP sessions counted against the session limit, across transports.",
+        sources.session_limiter.active() as f64,
+    );
+
+    (
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        out,
+    )
+        .into_response()
+}
+
+fn describe(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    describe(out, name, "counter", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    describe(out, name, "gauge", help);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn write_histogram(out: &mut String, name: &str, labels: &str, histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (le, count) in BUCKETS.iter().zip(&histogram.counts) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"{}\"}} {}",
+            name, labels, le, cumulative
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{}_bucket{{{},le=\"+Inf\"}} {}",
+        name, labels, histogram.count
+    );
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, histogram.sum);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, histogram.count);
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,157 @@
+//! Persistent store of `/points` resolutions.
+//!
+//! The office, grid and zone a coordinate maps to almost never change, so
+//! resolutions are kept in `points.json` under the data directory and reused
+//! across restarts, keyed by the rounded coordinates.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{PointsProps, coordinates::Coordinates};
+
+const FILE_NAME: &str = "points.json";
+
+/// Resolutions older than this are looked up again in case NWS has
+/// re-gridded the area.
+const MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Resolutions kept before the oldest ones are dropped.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct Entry {
+    /// Seconds since the Unix epoch.
+    resolved_at: u64,
+    properties: PointsProps,
+}
+
+#[derive(Debug)]
+pub struct PointsStore {
+    path: Option<PathBuf>,
+    entries: Mutex<HashMap<String, Entry>>,
+    /// Held while writing the file, so that writes happen one at a time and
+    /// the last one holds the latest entries.
+    write: tokio::sync::Mutex<()>,
+}
+
+impl Entry {
+    fn is_fresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.resolved_at) < MAX_AGE.as_secs()
+    }
+}
+
+impl PointsStore {
+    /// Opens the store in `dir`, creating it if needed. Falls back to an
+    /// in-memory store if the directory is unusable.
+    pub fn open(dir: &Path) -> Self {
+        let path = dir.join(FILE_NAME);
+
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            tracing::warn!(
+                "Cannot create data directory {}: {}; points will not be persisted",
+                dir.display(),
+                e
+            );
+            return Self::in_memory();
+        }
+
+        let mut entries: HashMap<String, Entry> = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring corrupt points store {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                tracing::warn!("Cannot read points store {}: {}", path.display(), e);
+                HashMap::new()
+            }
+        };
+        let now = now();
+        entries.retain(|_, entry| entry.is_fresh(now));
+        tracing::info!(
+            "Loaded {} points resolutions from {}",
+            entries.len(),
+            path.display()
+        );
+
+        Self {
+            path: Some(path),
+            entries: Mutex::new(entries),
+            write: tokio::sync::Mutex::default(),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            entries: Mutex::default(),
+            write: tokio::sync::Mutex::default(),
+        }
+    }
+
+    pub async fn get(&self, coordinates: &Coordinates) -> Option<PointsProps> {
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(&coordinates.to_string())?;
+        entry.is_fresh(now()).then(|| entry.properties.clone())
+    }
+
+    /// Records a resolution, dropping expired entries and, at
+    /// [`MAX_ENTRIES`], the oldest one.
+    pub async fn insert(&self, coordinates: &Coordinates, properties: PointsProps) {
+        {
+            let mut entries = self.entries.lock().unwrap();
+            let now = now();
+            entries.retain(|_, entry| entry.is_fresh(now));
+            let key = coordinates.to_string();
+            if entries.len() >= MAX_ENTRIES
+                && !entries.contains_key(&key)
+                && let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.resolved_at)
+                    .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+            entries.insert(
+                key,
+                Entry {
+                    resolved_at: now,
+                    properties,
+                },
+            );
+        }
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        // Snapshot once this write's turn comes, so a write that waited
+        // includes the entries inserted meanwhile; gets never wait on the file
+        let _write = self.write.lock().await;
+        let data = serde_json::to_vec(&*self.entries.lock().unwrap());
+        let written = match data {
+            Ok(data) => persist(path, data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = written {
+            tracing::warn!("Failed to write points store {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Replaces the file atomically so a crash never leaves it half written.
+async fn persist(path: &Path, data: Vec<u8>) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, data).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,270 @@
+//! Token-bucket rate limiting.
+//!
+//! [`ClientLimiter`] keeps one bucket per client at the HTTP layer, so a
+//! single chatty agent cannot monopolise the server; a second one per IP
+//! address limits failed authentication attempts. [`UpstreamBudget`] is a
+//! single bucket shared by every outbound NWS request, so that all clients
+//! together stay under a rate NWS tolerates from one address.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::auth::Principal;
+
+/// Client buckets are pruned once there are this many, dropping those that
+/// have refilled completely.
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Rate {
+    /// Sustained rate; zero disables the limit.
+    pub per_second: f64,
+    /// Requests allowed in a burst above the sustained rate.
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(rate: Rate) -> Self {
+        Self {
+            tokens: f64::from(rate.burst),
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, rate: Rate, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate.per_second).min(f64::from(rate.burst));
+        self.updated = now;
+    }
+
+    /// Takes a token, waiting up to `max_wait` for one to become available.
+    /// Returns how long the caller must wait before proceeding, or how long
+    /// until a token is available if that exceeds `max_wait`.
+    fn reserve(&mut self, rate: Rate, max_wait: Duration) -> Result<Duration, Duration> {
+        self.refill(rate, Instant::now());
+        let wait = if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / rate.per_second)
+        };
+        if wait > max_wait {
+            return Err(wait);
+        }
+        // Going negative queues the caller behind earlier reservations
+        self.tokens -= 1.0;
+        Ok(wait)
+    }
+}
+
+#[derive(Debug)]
+pub struct ClientLimiter {
+    rate: Rate,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl ClientLimiter {
+    pub fn new(rate: Rate) -> Arc<Self> {
+        Arc::new(Self {
+            rate,
+            buckets: Mutex::default(),
+        })
+    }
+
+    fn check(&self, client: &str) -> Result<(), Duration> {
+        self.with_bucket(client, |rate, bucket| {
+            bucket.reserve(rate, Duration::ZERO).map(|_| ())
+        })
+    }
+
+    /// Returns how long until `client` has a token again, without taking one.
+    fn exhausted(&self, client: &str) -> Option<Duration> {
+        self.with_bucket(client, |rate, bucket| {
+            bucket.refill(rate, Instant::now());
+            (bucket.tokens < 1.0)
+                .then(|| Duration::from_secs_f64((1.0 - bucket.tokens) / rate.per_second))
+        })
+    }
+
+    /// Takes a token from `client` after the fact, going negative if need be.
+    fn charge(&self, client: &str) {
+        self.with_bucket(client, |rate, bucket| {
+            bucket.refill(rate, Instant::now());
+            bucket.tokens -= 1.0;
+        })
+    }
+
+    fn with_bucket<T>(&self, client: &str, f: impl FnOnce(Rate, &mut TokenBucket) -> T) -> T {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_IDLE_BUCKETS {
+            let now = Instant::now();
+            let (rate, burst) = (self.rate, f64::from(self.rate.burst));
+            buckets.retain(|_, bucket| {
+                bucket.refill(rate, now);
+                bucket.tokens < burst
+            });
+        }
+        let bucket = buckets
+            .entry(client.to_string())
+            .or_insert_with(|| TokenBucket::full(self.rate));
+        f(self.rate, bucket)
+    }
+}
+
+/// Axum middleware limiting each client's request rate. Clients are told
+/// apart by authenticated identity, or else by IP address: an MCP session ID
+/// can't identify a client, since anyone can open sessions at will.
+pub async fn limit_clients(
+    State(limiter): State<Arc<ClientLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.rate.per_second <= 0.0 {
+        return next.run(request).await;
+    }
+
+    let client = client_key(&request);
+    match limiter.check(&client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_in) => too_many_requests(&client, retry_in),
+    }
+}
+
+/// Axum middleware limiting failed authentication attempts per IP address.
+/// It sits outside the bearer token check, so guessing tokens costs the
+/// client its budget before any signature is verified, while requests that
+/// authenticate don't count against the address.
+pub async fn limit_failed_auth(
+    State(limiter): State<Arc<ClientLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.rate.per_second <= 0.0 {
+        return next.run(request).await;
+    }
+
+    let client = ip_key(&request);
+    if let Some(retry_in) = limiter.exhausted(&client) {
+        return too_many_requests(&client, retry_in);
+    }
+    let response = next.run(request).await;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        limiter.charge(&client);
+    }
+    response
+}
+
+fn too_many_requests(client: &str, retry_in: Duration) -> Response {
+    let retry_secs = retry_in.as_secs_f64().ceil().max(1.0) as u64;
+    tracing::warn!(client, retry_secs, "Client rate limit exceeded");
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        format!("Rate limit exceeded. Try again in {} seconds.", retry_secs),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_secs));
+    response
+}
+
+fn client_key(request: &Request) -> String {
+    match request.extensions().get::<Principal>() {
+        Some(principal) => format!("subject:{}", principal.subject),
+        None => ip_key(request),
+    }
+}
+
+fn ip_key(request: &Request) -> String {
+    match request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Budget for outbound NWS requests, shared by all clients. Requests beyond
+/// the rate wait their turn for up to `max_queue`, and are rejected after
+/// that.
+#[derive(Debug)]
+pub struct UpstreamBudget {
+    rate: Rate,
+    max_queue: Duration,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl UpstreamBudget {
+    pub fn new(rate: Rate, max_queue: Duration) -> Self {
+        Self {
+            rate,
+            max_queue,
+            bucket: Mutex::new(TokenBucket::full(rate)),
+        }
+    }
+
+    /// Waits for permission to send one request. Returns how long until the
+    /// budget allows another request if the queue is too long.
+    pub async fn acquire(&self) -> Result<(), Duration> {
+        if self.rate.per_second <= 0.0 {
+            return Ok(());
+        }
+        let wait = self
+            .bucket
+            .lock()
+            .unwrap()
+            .reserve(self.rate, self.max_queue)?;
+        if !wait.is_zero() {
+            tracing::debug!("Upstream budget exhausted; queueing request for {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(burst: u32) -> Arc<ClientLimiter> {
+        ClientLimiter::new(Rate {
+            per_second: 0.001,
+            burst,
+        })
+    }
+
+    #[test]
+    fn check_takes_tokens_until_the_burst_is_spent() {
+        let limiter = limiter(2);
+        assert!(limiter.check("ip:192.0.2.1").is_ok());
+        assert!(limiter.check("ip:192.0.2.1").is_ok());
+        assert!(limiter.check("ip:192.0.2.1").is_err());
+        assert!(limiter.check("ip:192.0.2.2").is_ok());
+    }
+
+    #[test]
+    fn only_charged_attempts_exhaust_an_address() {
+        let limiter = limiter(2);
+        assert!(limiter.exhausted("ip:192.0.2.1").is_none());
+        assert!(limiter.exhausted("ip:192.0.2.1").is_none());
+        limiter.charge("ip:192.0.2.1");
+        assert!(limiter.exhausted("ip:192.0.2.1").is_none());
+        limiter.charge("ip:192.0.2.1");
+        let retry_in = limiter.exhausted("ip:192.0.2.1").unwrap();
+        assert!(retry_in > Duration::from_secs(900), "{retry_in:?}");
+    }
+}
@@ -0,0 +1,332 @@
+//! Active alerts published as MCP resources, with update notifications.
+//!
+//! - `weather://alerts/{state}`: alerts for a state or territory
+//! - `weather://alerts/point/{latitude},{longitude}`: alerts covering a point
+//!
+//! A background task re-reads every subscribed resource each
+//! [`POLL_INTERVAL`] and sends `notifications/resources/updated` to the
+//! subscribers of those whose set of active alerts has changed. Subscribers
+//! whose session has ended are dropped at each poll.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use percent_encoding::percent_decode_str;
+use rmcp::{
+    Peer, RoleServer,
+    model::{
+        AnnotateAble, RawResource, RawResourceTemplate, Resource, ResourceTemplate,
+        ResourceUpdatedNotificationParam,
+    },
+};
+
+use crate::{Weather, coordinates::Coordinates, gazetteer};
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Subscriptions one session may hold at a time.
+const MAX_SUBSCRIPTIONS_PER_SESSION: usize = 20;
+const URI_PREFIX: &str = "weather://alerts/";
+pub const MIME_TYPE: &str = "text/plain";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertResource {
+    State(&'static str),
+    Point(Coordinates),
+}
+
+impl AlertResource {
+    pub fn parse(uri: &str) -> Result<Self, String> {
+        let unknown = || {
+            format!(
+                "Unknown resource {}: expected {}{{state}} or {}point/{{latitude}},{{longitude}}.",
+                uri, URI_PREFIX, URI_PREFIX
+            )
+        };
+        let path = uri.strip_prefix(URI_PREFIX).ok_or_else(unknown)?;
+        // Clients may percent-encode names such as "new%20york" and the comma
+        // between coordinates
+        let path = percent_decode_str(path)
+            .decode_utf8()
+            .map_err(|_| unknown())?;
+
+        if let Some(point) = path.strip_prefix("point/") {
+            let (latitude, longitude) = point.split_once(',').ok_or_else(unknown)?;
+            let latitude = latitude.trim().parse().map_err(|_| unknown())?;
+            let longitude = longitude.trim().parse().map_err(|_| unknown())?;
+            return Coordinates::new(latitude, longitude).map(AlertResource::Point);
+        }
+        // Form-style encoding writes spaces as '+', as in "new+york"
+        gazetteer::state_code(&path.replace('+', " "))
+            .map(AlertResource::State)
+            .ok_or_else(unknown)
+    }
+
+    /// The canonical URI, so that equivalent URIs share one subscription.
+    pub fn uri(&self) -> String {
+        match self {
+            AlertResource::State(code) => format!("{}{}", URI_PREFIX, code),
+            AlertResource::Point(coordinates) => format!("{}point/{}", URI_PREFIX, coordinates),
+        }
+    }
+}
+
+/// One resource per state and territory.
+pub fn resources() -> Vec<Resource> {
+    gazetteer::STATES
+        .iter()
+        .map(|(code, name)| {
+            RawResource {
+                description: Some(format!("Active weather alerts for {}", name)),
+                mime_type: Some(MIME_TYPE.to_string()),
+                ..RawResource::new(AlertResource::State(code).uri(), format!("{} alerts", name))
+            }
+            .no_annotation()
+        })
+        .collect()
+}
+
+pub fn templates() -> Vec<ResourceTemplate> {
+    [
+        (
+            format!("{}{{state}}", URI_PREFIX),
+            "State alerts",
+            "Active weather alerts for a US state or territory, by two-letter code",
+        ),
+        (
+            format!("{}point/{{latitude}},{{longitude}}", URI_PREFIX),
+            "Point alerts",
+            "Active weather alerts covering a point given in decimal degrees",
+        ),
+    ]
+    .into_iter()
+    .map(|(uri_template, name, description)| {
+        RawResourceTemplate {
+            uri_template,
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            mime_type: Some(MIME_TYPE.to_string()),
+        }
+        .no_annotation()
+    })
+    .collect()
+}
+
+/// Identifies the session a handler serves, for tracking its subscriptions.
+pub fn next_subscriber_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug)]
+struct Subscription {
+    resource: AlertResource,
+    /// Each session's peer and the URI it subscribed with, which
+    /// notifications must repeat.
+    subscribers: HashMap<u64, (Peer<RoleServer>, String)>,
+    /// Sorted IDs of the alerts active at the last poll, if any succeeded.
+    alert_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    /// By canonical URI.
+    entries: Mutex<HashMap<String, Subscription>>,
+}
+
+impl Subscriptions {
+    /// Adds a subscriber, recording `alert_ids` as the current state if the
+    /// resource had no subscribers yet.
+    pub fn subscribe(
+        &self,
+        resource: AlertResource,
+        uri_as_given: String,
+        subscriber: u64,
+        peer: Peer<RoleServer>,
+        alert_ids: Option<Vec<String>>,
+    ) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let uri = resource.uri();
+        let held = entries
+            .iter()
+            .filter(|(held_uri, subscription)| {
+                **held_uri != uri && subscription.subscribers.contains_key(&subscriber)
+            })
+            .count();
+        if held >= MAX_SUBSCRIPTIONS_PER_SESSION {
+            return Err(format!(
+                "Too many subscriptions: a session may subscribe to at most {} resources.",
+                MAX_SUBSCRIPTIONS_PER_SESSION
+            ));
+        }
+
+        entries
+            .entry(uri)
+            .or_insert_with(|| Subscription {
+                resource,
+                subscribers: HashMap::new(),
+                alert_ids,
+            })
+            .subscribers
+            .insert(subscriber, (peer, uri_as_given));
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, resource: &AlertResource, subscriber: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        let uri = resource.uri();
+        if let Some(subscription) = entries.get_mut(&uri) {
+            subscription.subscribers.remove(&subscriber);
+            if subscription.subscribers.is_empty() {
+                entries.remove(&uri);
+            }
+        }
+    }
+
+    /// Drops every subscription of a session that has gone away.
+    fn remove_subscriber(&self, subscriber: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, subscription| {
+            subscription.subscribers.remove(&subscriber);
+            !subscription.subscribers.is_empty()
+        });
+    }
+
+    /// Drops the subscribers whose session has ended without unsubscribing,
+    /// and the resources left without subscribers.
+    fn remove_closed(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|uri, subscription| {
+            subscription.subscribers.retain(|_, (peer, _)| {
+                let closed = peer.is_transport_closed();
+                if closed {
+                    tracing::debug!("Dropping subscriber to {} whose session ended", uri);
+                }
+                !closed
+            });
+            !subscription.subscribers.is_empty()
+        });
+    }
+
+    fn watched(&self) -> Vec<AlertResource> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .values()
+            .map(|subscription| subscription.resource)
+            .collect()
+    }
+
+    /// Records the alerts now active for a resource. Returns the subscribers
+    /// to notify if they differ from the last poll.
+    fn update(
+        &self,
+        resource: &AlertResource,
+        alert_ids: Vec<String>,
+    ) -> Vec<(u64, Peer<RoleServer>, String)> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(subscription) = entries.get_mut(&resource.uri()) else {
+            return Vec::new();
+        };
+        let previous = subscription.alert_ids.replace(alert_ids);
+        if previous.is_none() || previous == subscription.alert_ids {
+            return Vec::new();
+        }
+        subscription
+            .subscribers
+            .iter()
+            .map(|(subscriber, (peer, uri))| (*subscriber, peer.clone(), uri.clone()))
+            .collect()
+    }
+}
+
+/// Polls subscribed resources until the process exits.
+pub async fn poll(weather: Weather) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        weather.subscriptions.remove_closed();
+        for resource in weather.subscriptions.watched() {
+            let alert_ids = match weather.alert_ids(&resource).await {
+                Ok((alert_ids, None)) => alert_ids,
+                // Comparing against a stale copy would report changes late
+                // or not at all; wait for NWS to come back instead
+                Ok((_, Some(_))) => {
+                    tracing::warn!("Cannot poll {}: NWS is unavailable", resource.uri());
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Cannot poll {}: {}", resource.uri(), e);
+                    continue;
+                }
+            };
+
+            for (subscriber, peer, uri) in weather.subscriptions.update(&resource, alert_ids) {
+                tracing::info!("Alerts changed for {}, notifying subscriber", uri);
+                let notified = peer
+                    .notify_resource_updated(ResourceUpdatedNotificationParam { uri: uri.clone() })
+                    .await;
+                if let Err(e) = notified {
+                    tracing::debug!("Dropping subscriber to {}: {}", uri, e);
+                    weather.subscriptions.remove_subscriber(subscriber);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_state_codes_and_names() {
+        for uri in [
+            "weather://alerts/NY",
+            "weather://alerts/ny",
+            "weather://alerts/New York",
+            "weather://alerts/new%20york",
+            "weather://alerts/new+york",
+        ] {
+            assert_eq!(
+                AlertResource::parse(uri),
+                Ok(AlertResource::State("NY")),
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_points_with_encoded_commas() {
+        for uri in [
+            "weather://alerts/point/47.6062,-122.3321",
+            "weather://alerts/point/47.6062%2C-122.3321",
+            "weather://alerts/point/47.6062%2c%20-122.3321",
+        ] {
+            let resource = AlertResource::parse(uri).unwrap();
+            assert_eq!(
+                resource.uri(),
+                "weather://alerts/point/47.6062,-122.3321",
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_resources() {
+        for uri in [
+            "weather://forecast/WA",
+            "weather://alerts/XX",
+            "weather://alerts/%FF",
+            "weather://alerts/point/47.6062",
+        ] {
+            let error = AlertResource::parse(uri).unwrap_err();
+            assert!(error.starts_with("Unknown resource"), "{error}");
+        }
+        assert!(AlertResource::parse("weather://alerts/point/51.5,-0.1").is_err());
+    }
+}
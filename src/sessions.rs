@@ -0,0 +1,97 @@
+//! Limit on concurrent MCP sessions, shared by the streamable HTTP and legacy
+//! SSE endpoints.
+//!
+//! Each session's `Weather` handler holds a [`SessionPermit`]; the permit is
+//! released when the session ends and its handler is dropped. Requests that
+//! would open a new session are turned away with 503 while the limit is
+//! reached.
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{MCP_PATH, SSE_PATH};
+
+/// Seconds a client is asked to wait before retrying a rejected session.
+const RETRY_AFTER_SECS: u64 = 5;
+
+#[derive(Debug)]
+pub struct SessionLimiter {
+    active: AtomicUsize,
+    max: usize,
+}
+
+impl SessionLimiter {
+    pub fn new(max: usize) -> Arc<Self> {
+        Arc::new(Self {
+            active: AtomicUsize::new(0),
+            max,
+        })
+    }
+
+    /// Counts a new session. Always succeeds: the limit is enforced before
+    /// the session is created, by [`reject_when_full`].
+    pub fn acquire(self: &Arc<Self>) -> SessionPermit {
+        let active = self.active.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::debug!(active, max = self.max, "Session opened");
+        SessionPermit(self.clone())
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn is_full(&self) -> bool {
+        self.active() >= self.max
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionPermit(Arc<SessionLimiter>);
+
+impl Drop for SessionPermit {
+    fn drop(&mut self) {
+        let active = self.0.active.fetch_sub(1, Ordering::Relaxed) - 1;
+        tracing::debug!(active, max = self.0.max, "Session closed");
+    }
+}
+
+/// Axum middleware rejecting requests that would open a session while the
+/// limit is reached: a streamable HTTP POST without `Mcp-Session-Id`, or an
+/// SSE stream request.
+pub async fn reject_when_full(
+    State(limiter): State<Arc<SessionLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let opens_session = match *request.method() {
+        Method::POST => path == MCP_PATH && !request.headers().contains_key("mcp-session-id"),
+        Method::GET => path == SSE_PATH,
+        _ => false,
+    };
+    if opens_session && limiter.is_full() {
+        tracing::warn!(max = limiter.max, "Rejecting new session; limit reached");
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!(
+                "Too many active sessions (limit {}). Try again later.",
+                limiter.max
+            ),
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+        return response;
+    }
+    next.run(request).await
+}
@@ -0,0 +1,360 @@
+//! Optional export of tracing spans to an OpenTelemetry collector over
+//! OTLP/HTTP with the JSON encoding.
+//!
+//! [`OtlpLayer`] assigns W3C trace and span IDs to this crate's spans and
+//! hands each finished span to a background task, which posts them in batches
+//! to `<endpoint>/v1/traces`. A root span with a `traceparent` field, set on
+//! creation or recorded before any child span starts, continues the trace it
+//! names, which is how a trace started by the MCP client carries over into the
+//! tool call. Spans may set `otel.name`, `otel.kind` and
+//! `otel.status_code` to control their exported name, kind and status; every
+//! other field becomes an attribute. With coordinate redaction on, string
+//! attributes such as `coordinates` and `url` are coarsened as in the logs.
+
+use std::{
+    fmt::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::mpsc;
+use tracing::{
+    Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
+
+use crate::logging::coarsen_coordinates;
+
+/// Spans are sent at least this often.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+/// Spans per export request.
+const MAX_BATCH: usize = 512;
+/// Finished spans waiting for export; more are dropped.
+const MAX_QUEUE: usize = 4096;
+
+#[derive(Debug, Clone)]
+enum AttributeValue {
+    String(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+#[derive(Debug, Default)]
+struct Fields {
+    name: Option<String>,
+    kind: Option<String>,
+    traceparent: Option<String>,
+    error: bool,
+    attributes: Vec<(&'static str, AttributeValue)>,
+}
+
+impl Fields {
+    fn set(&mut self, field: &Field, value: AttributeValue) {
+        match (field.name(), value) {
+            ("otel.name", AttributeValue::String(name)) => self.name = Some(name),
+            ("otel.kind", AttributeValue::String(kind)) => self.kind = Some(kind),
+            ("traceparent", AttributeValue::String(header)) => self.traceparent = Some(header),
+            ("otel.status_code", AttributeValue::String(code)) => self.error = code == "ERROR",
+            (key, value) => {
+                self.attributes.retain(|(existing, _)| *existing != key);
+                self.attributes.push((key, value));
+            }
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, AttributeValue::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, AttributeValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(field, AttributeValue::Int(value as i64));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.set(field, AttributeValue::Double(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, AttributeValue::Bool(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.set(field, AttributeValue::String(format!("{:?}", value)));
+    }
+}
+
+/// A span in progress, kept in the span's extensions.
+#[derive(Debug)]
+struct SpanData {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: &'static str,
+    start: SystemTime,
+    fields: Fields,
+    error: bool,
+}
+
+#[derive(Debug)]
+pub struct OtlpLayer {
+    sender: mpsc::Sender<(SpanData, SystemTime)>,
+}
+
+impl OtlpLayer {
+    /// Creates the layer and starts the export task. Must be called within a
+    /// Tokio runtime. `redact_coordinates` truncates coordinates in exported
+    /// attributes to one decimal place.
+    pub fn new(endpoint: &str, redact_coordinates: bool) -> Self {
+        let (sender, receiver) = mpsc::channel(MAX_QUEUE);
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        tokio::spawn(export(url, receiver, redact_coordinates));
+        Self { sender }
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+
+        let parent = span.parent().and_then(|parent| {
+            let extensions = parent.extensions();
+            let data = extensions.get::<SpanData>()?;
+            Some((data.trace_id, data.span_id))
+        });
+        let (trace_id, parent_span_id) = match parent {
+            Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+            None => match fields.traceparent.as_deref().and_then(parse_traceparent) {
+                Some((trace_id, span_id)) => (trace_id, Some(span_id)),
+                None => (rand::random(), None),
+            },
+        };
+
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id: rand::random(),
+            parent_span_id,
+            name: span.name(),
+            start: SystemTime::now(),
+            fields,
+            error: false,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let has_parent = span.parent().is_some();
+        let mut extensions = span.extensions_mut();
+        let Some(data) = extensions.get_mut::<SpanData>() else {
+            return;
+        };
+        let had_traceparent = data.fields.traceparent.is_some();
+        values.record(&mut data.fields);
+
+        // A root span may adopt a remote parent until it has children
+        if !has_parent
+            && !had_traceparent
+            && let Some((trace_id, span_id)) = data
+                .fields
+                .traceparent
+                .as_deref()
+                .and_then(parse_traceparent)
+        {
+            data.trace_id = trace_id;
+            data.parent_span_id = Some(span_id);
+        }
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+        // An error logged inside a span marks the span as failed
+        if *event.metadata().level() == tracing::Level::ERROR
+            && let Some(span) = ctx.event_span(event)
+            && let Some(data) = span.extensions_mut().get_mut::<SpanData>()
+        {
+            data.error = true;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(data) = span.extensions_mut().remove::<SpanData>() else {
+            return;
+        };
+        // Never block the instrumented code on a slow collector
+        let _ = self.sender.try_send((data, SystemTime::now()));
+    }
+}
+
+/// Parses a W3C `traceparent` header into trace ID and parent span ID.
+fn parse_traceparent(header: &str) -> Option<([u8; 16], [u8; 8])> {
+    let mut parts = header.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    parts.next()?; // flags
+    if version.len() != 2 || version == "ff" {
+        return None;
+    }
+    let trace_id: [u8; 16] = decode_hex(trace_id)?.try_into().ok()?;
+    let span_id: [u8; 8] = decode_hex(span_id)?.try_into().ok()?;
+    // All-zero IDs are invalid
+    if trace_id == [0; 16] || span_id == [0; 8] {
+        return None;
+    }
+    Some((trace_id, span_id))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{b:02x}");
+        hex
+    })
+}
+
+async fn export(
+    url: String,
+    mut receiver: mpsc::Receiver<(SpanData, SystemTime)>,
+    redact_coordinates: bool,
+) {
+    let client = reqwest::Client::new();
+    let mut batch = Vec::new();
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+    loop {
+        tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    if batch.len() < MAX_BATCH {
+                        continue;
+                    }
+                }
+                None => {
+                    send(&client, &url, &mut batch, redact_coordinates).await;
+                    return;
+                }
+            },
+            _ = interval.tick() => {}
+        }
+        send(&client, &url, &mut batch, redact_coordinates).await;
+    }
+}
+
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    batch: &mut Vec<(SpanData, SystemTime)>,
+    redact_coordinates: bool,
+) {
+    if batch.is_empty() {
+        return;
+    }
+    let body = encode(batch.drain(..), redact_coordinates);
+    match client.post(url).json(&body).send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => eprintln!(
+            "OTLP export to {} failed with status {}",
+            url,
+            response.status()
+        ),
+        // Not logged through tracing, which could feed back into the exporter
+        Err(e) => eprintln!("OTLP export to {} failed: {}", url, e),
+    }
+}
+
+fn encode(
+    spans: impl Iterator<Item = (SpanData, SystemTime)>,
+    redact_coordinates: bool,
+) -> serde_json::Value {
+    let spans: Vec<_> = spans
+        .map(|(mut data, end)| {
+            if redact_coordinates {
+                for (_, value) in &mut data.fields.attributes {
+                    if let AttributeValue::String(s) = value {
+                        *s = coarsen_coordinates(s);
+                    }
+                }
+            }
+            let kind = match data.fields.kind.as_deref() {
+                Some("server") => 2,
+                Some("client") => 3,
+                Some("producer") => 4,
+                Some("consumer") => 5,
+                _ => 1,
+            };
+            let mut span = serde_json::json!({
+                "traceId": encode_hex(&data.trace_id),
+                "spanId": encode_hex(&data.span_id),
+                "name": data.fields.name.as_deref().unwrap_or(data.name),
+                "kind": kind,
+                "startTimeUnixNano": unix_nanos(data.start).to_string(),
+                "endTimeUnixNano": unix_nanos(end).to_string(),
+                "attributes": data.fields.attributes.iter().map(|(key, value)| attribute(key, value)).collect::<Vec<_>>(),
+                "status": { "code": if data.error || data.fields.error { 2 } else { 0 } },
+            });
+            if let Some(parent) = data.parent_span_id {
+                span["parentSpanId"] = encode_hex(&parent).into();
+            }
+            span
+        })
+        .collect();
+
+    serde_json::json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attribute("service.name", &AttributeValue::String(env!("CARGO_PKG_NAME").to_string())),
+                    attribute("service.version", &AttributeValue::String(env!("CARGO_PKG_VERSION").to_string())),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": env!("CARGO_PKG_NAME") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+fn attribute(key: &str, value: &AttributeValue) -> serde_json::Value {
+    let value = match value {
+        AttributeValue::String(s) => serde_json::json!({ "stringValue": s }),
+        // OTLP/JSON encodes 64-bit integers as strings
+        AttributeValue::Int(i) => serde_json::json!({ "intValue": i.to_string() }),
+        AttributeValue::Double(d) => serde_json::json!({ "doubleValue": d }),
+        AttributeValue::Bool(b) => serde_json::json!({ "boolValue": b }),
+    };
+    serde_json::json!({ "key": key, "value": value })
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
@@ -0,0 +1,42 @@
+//! In-memory cache of zone boundaries.
+//!
+//! Zone-based alerts carry no polygon of their own, only the URLs of the
+//! zones they affect. Boundaries change only when NWS redraws its zones, so
+//! they are kept parsed for the life of the process.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::geometry::Geometry;
+
+/// Zones kept before arbitrary ones are dropped.
+const MAX_ZONES: usize = 2000;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ZoneResponse {
+    pub geometry: Option<Geometry>,
+}
+
+#[derive(Debug, Default)]
+pub struct ZoneCache {
+    /// By zone URL.
+    entries: Mutex<HashMap<String, Arc<Geometry>>>,
+}
+
+impl ZoneCache {
+    pub fn get(&self, url: &str) -> Option<Arc<Geometry>> {
+        self.entries.lock().unwrap().get(url).cloned()
+    }
+
+    pub fn insert(&self, url: &str, geometry: Arc<Geometry>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ZONES
+            && let Some(evicted) = entries.keys().next().cloned()
+        {
+            entries.remove(&evicted);
+        }
+        entries.insert(url.to_string(), geometry);
+    }
+}